@@ -0,0 +1,33 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import bevy_voronoi::flood::{cell_edge_distance, unpack_second_seed}
+
+@group(0) @binding(0) var seed_texture: texture_2d<f32>;
+@group(0) @binding(1) var object_texture: texture_2d<u32>;
+@group(0) @binding(2) var second_texture: texture_2d<u32>;
+
+// Width of the cell borders in texels of the Voronoi texture
+const BORDER_WIDTH: f32 = 2.;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(seed_texture));
+    let texel = vec2<i32>(in.uv * size);
+
+    let seed = textureLoad(seed_texture, texel, 0).xy;
+    let object = textureLoad(object_texture, texel, 0).x;
+    let second_seed = unpack_second_seed(textureLoad(second_texture, texel, 0));
+
+    // Distance to the border shared with the cell of the second seed, positive inside the
+    // cell of the nearest seed
+    let edge_dist = cell_edge_distance(vec2<f32>(texel) + 0.5, seed, second_seed);
+    let border = 1. - smoothstep(0., BORDER_WIDTH, edge_dist);
+
+    return vec4<f32>(mix(cell_color(object), vec3(0.), border), 1.);
+}
+
+// Stable pseudo-random color per object id
+fn cell_color(object: u32) -> vec3<f32> {
+    let hash = object * 2654435761u;
+    let rgb = vec3<u32>(hash >> 16u, hash >> 8u, hash) & vec3(255u);
+    return 0.3 + 0.6 * vec3<f32>(rgb) / 255.;
+}
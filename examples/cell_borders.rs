@@ -0,0 +1,262 @@
+use bevy::{
+    core_pipeline::{
+        core_2d::graph::{Core2d, Node2d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::{query::QueryItem, system::lifetimeless::Read},
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::texture_2d, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, MultisampleState,
+            Operations, PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, ShaderStages, SpecializedRenderPipeline,
+            SpecializedRenderPipelines, TextureFormat, TextureSampleType,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::{ExtractedView, ViewTarget},
+        Render, RenderApp, RenderSet,
+    },
+};
+use bevy_voronoi::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, CellBordersPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, orbit_seeds)
+        .run();
+}
+
+/// Number of point seeds along each axis of the grid they orbit around.
+const GRID: i32 = 6;
+const SPACING: f32 = 120.;
+
+#[derive(Component)]
+struct Orbit {
+    center: Vec2,
+    radius: f32,
+    speed: f32,
+}
+
+fn setup(mut commands: Commands) {
+    // The second seed gives every texel the nearest seed of another cell, which the
+    // composite shader turns into the distance to the cell border
+    commands.spawn((Camera2d, VoronoiCamera::default().with_second_seed()));
+
+    for x in 0..GRID {
+        for y in 0..GRID {
+            let center = (Vec2::new(x as f32, y as f32) - (GRID - 1) as f32 / 2.) * SPACING;
+            let phase = (x * GRID + y) as f32;
+            commands.spawn((
+                VoronoiSeed::default(),
+                Orbit {
+                    center,
+                    radius: SPACING * 0.3,
+                    speed: 0.5 + (phase * 0.37).fract(),
+                },
+                Transform::from_translation(center.extend(0.0)),
+            ));
+        }
+    }
+}
+
+fn orbit_seeds(time: Res<Time>, mut seeds: Query<(&Orbit, &mut Transform)>) {
+    for (orbit, mut transform) in &mut seeds {
+        let angle = time.elapsed_secs() * orbit.speed;
+        let offset = Vec2::from_angle(angle) * orbit.radius;
+        transform.translation = (orbit.center + offset).extend(0.0);
+    }
+}
+
+struct CellBordersPlugin;
+
+impl Plugin for CellBordersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Voronoi2dPlugin);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<SpecializedRenderPipelines<CompositePipeline>>()
+            .add_systems(
+                Render,
+                prepare_composite_pipeline.in_set(RenderSet::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<CompositeNode>>(Core2d, CompositePassLabel)
+            .add_render_graph_edges(Core2d, (Node2d::EndMainPass, CompositePassLabel));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<CompositePipeline>();
+    }
+}
+
+fn prepare_composite_pipeline(
+    mut commands: Commands,
+    view_query: Query<(Entity, &ExtractedView)>,
+    mut composite_pipelines: ResMut<SpecializedRenderPipelines<CompositePipeline>>,
+    composite_pipeline: Res<CompositePipeline>,
+    pipeline_cache: Res<PipelineCache>,
+) {
+    for (entity, view) in &view_query {
+        let composite_pipeline_id = composite_pipelines.specialize(
+            &pipeline_cache,
+            &composite_pipeline,
+            CompositePipelineKey { hdr: view.hdr },
+        );
+
+        commands
+            .entity(entity)
+            .insert(ViewCompositePipelineId(composite_pipeline_id));
+    }
+}
+
+#[derive(Component)]
+pub struct ViewCompositePipelineId(pub CachedRenderPipelineId);
+
+#[derive(Resource)]
+pub struct CompositePipeline {
+    pub shader: Handle<Shader>,
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for CompositePipeline {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            shader: world.resource::<AssetServer>().load("cell_borders.wgsl"),
+            layout: world.resource::<RenderDevice>().create_bind_group_layout(
+                "composite_bind_group_layout",
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::FRAGMENT,
+                    (
+                        texture_2d(TextureSampleType::Float { filterable: false }),
+                        texture_2d(TextureSampleType::Uint),
+                        texture_2d(TextureSampleType::Uint),
+                    ),
+                ),
+            ),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Clone)]
+pub struct CompositePipelineKey {
+    pub hdr: bool,
+}
+
+impl SpecializedRenderPipeline for CompositePipeline {
+    type Key = CompositePipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("composite_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: fullscreen_shader_vertex_state(),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: if key.hdr {
+                        ViewTarget::TEXTURE_FORMAT_HDR
+                    } else {
+                        TextureFormat::bevy_default()
+                    },
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+}
+
+#[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CompositePassLabel;
+
+#[derive(Default)]
+struct CompositeNode;
+impl ViewNode for CompositeNode {
+    type ViewQuery = (
+        Read<ExtractedCamera>,
+        Read<ViewTarget>,
+        Read<VoronoiTexture>,
+        Read<ViewCompositePipelineId>,
+    );
+
+    fn run<'w>(
+        &self,
+        _: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, target, flood_textures, composite_pipeline_id): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let composite_pipeline = world.resource::<CompositePipeline>();
+
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(composite_pipeline_id.0)
+        else {
+            return Ok(());
+        };
+
+        let (Some(result), Some(object_result), Some(second_result)) = (
+            flood_textures.result(),
+            flood_textures.object_result(),
+            flood_textures.second_result(),
+        ) else {
+            return Ok(());
+        };
+
+        let post_process = target.post_process_write();
+
+        let bind_group = render_context.render_device().create_bind_group(
+            "composite_bind_group",
+            &composite_pipeline.layout,
+            &BindGroupEntries::sequential((
+                &result.default_view,
+                &object_result.default_view,
+                &second_result.default_view,
+            )),
+        );
+
+        let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("composite_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            ..default()
+        });
+
+        if let Some(viewport) = camera.viewport.as_ref() {
+            pass.set_camera_viewport(viewport);
+        }
+
+        pass.set_render_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+};
+use bevy_voronoi::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, Voronoi2dPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, draw_distance)
+        .run();
+}
+
+/// Side of the level region covered by the off-screen camera in world units.
+const REGION: f32 = 1024.;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn(Camera2d);
+
+    // The off-screen camera only runs the Voronoi passes, its render target is never drawn
+    // to and can be tiny since the field has a fixed resolution
+    let mut render_target = Image::new_fill(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    render_target.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+
+    commands.spawn((
+        VoronoiOffscreen,
+        VoronoiReadback,
+        VoronoiCamera::default()
+            .with_resolution(UVec2::splat(512))
+            .with_world_rect(Rect::from_center_size(Vec2::ZERO, Vec2::splat(REGION))),
+        Camera {
+            target: images.add(render_target).into(),
+            ..default()
+        },
+    ));
+
+    let color = materials.add(Color::srgb(0.3, 0.5, 0.9));
+    let shapes = [
+        (meshes.add(Circle::new(60.0)), Vec2::new(-250.0, 120.0)),
+        (
+            meshes.add(Rectangle::new(80.0, 200.0)),
+            Vec2::new(0.0, -100.0),
+        ),
+        (
+            meshes.add(RegularPolygon::new(70.0, 5)),
+            Vec2::new(260.0, 80.0),
+        ),
+    ];
+
+    for (shape, position) in shapes {
+        commands.spawn((
+            Mesh2d(shape),
+            MeshMaterial2d(color.clone()),
+            VoronoiMaterial::default(),
+            Transform::from_translation(position.extend(0.0)),
+        ));
+    }
+}
+
+/// Draws the distance to the nearest shape around the cursor, read from the off-screen
+/// field regardless of what the main camera shows.
+fn draw_distance(
+    mut gizmos: Gizmos,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), Without<VoronoiOffscreen>>,
+    field: Single<&VoronoiReadbackField, With<VoronoiOffscreen>>,
+) {
+    let (camera, camera_transform) = *camera;
+
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    let (Some(distance), Some(seed)) = (field.sample_distance(cursor), field.nearest_seed(cursor))
+    else {
+        return;
+    };
+
+    let color = if distance < 0.0 {
+        Color::srgb(0.9, 0.3, 0.3)
+    } else {
+        Color::WHITE
+    };
+    gizmos.circle_2d(cursor, distance.abs(), color);
+    gizmos.line_2d(cursor, seed, color);
+}
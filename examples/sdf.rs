@@ -228,6 +228,10 @@ impl ViewNode for CompositeNode {
             return Ok(());
         };
 
+        let Some(result) = flood_textures.result() else {
+            return Ok(());
+        };
+
         let post_process = target.post_process_write();
         let sampler = render_context
             .render_device()
@@ -236,7 +240,7 @@ impl ViewNode for CompositeNode {
         let bind_group = render_context.render_device().create_bind_group(
             "composite_bind_group",
             &composite_pipeline.layout,
-            &BindGroupEntries::sequential((&flood_textures.input().default_view, &sampler)),
+            &BindGroupEntries::sequential((&result.default_view, &sampler)),
         );
 
         let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
@@ -0,0 +1,1166 @@
+//! CPU reference implementation of the mask, seed and flood passes.
+//!
+//! Produces the same RGBA layout as [`VoronoiTexture`](crate::prelude::VoronoiTexture)
+//! without a render device, so fields can be computed headless.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        mesh::{PrimitiveTopology, VertexAttributeValues},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use half::f16;
+
+use crate::{
+    flood::{DistanceMetric, JfaSchedule, SeedMode, VoronoiWeight},
+    plugin::VoronoiMaterial,
+};
+
+const NULL_SEED: Vec2 = Vec2::splat(-1.0);
+
+/// Coverage, object id and weight targets, the CPU counterpart of the mask pass output.
+#[derive(Clone, Debug)]
+pub struct AlphaMask {
+    size: UVec2,
+    data: Vec<f32>,
+    objects: Vec<u32>,
+    weights: Vec<VoronoiWeight>,
+}
+
+impl AlphaMask {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            data: vec![0.0; (size.x * size.y) as usize],
+            objects: vec![0; (size.x * size.y) as usize],
+            weights: vec![VoronoiWeight::default(); (size.x * size.y) as usize],
+        }
+    }
+
+    pub fn from_fn(size: UVec2, f: impl Fn(UVec2) -> f32) -> Self {
+        let mut mask = Self::new(size);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                mask.set(UVec2::new(x, y), f(UVec2::new(x, y)));
+            }
+        }
+        mask
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn get(&self, texel: UVec2) -> f32 {
+        self.data[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    pub fn set(&mut self, texel: UVec2, alpha: f32) {
+        self.data[(texel.y * self.size.x + texel.x) as usize] = alpha;
+    }
+
+    pub fn object(&self, texel: UVec2) -> u32 {
+        self.objects[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    pub fn set_object(&mut self, texel: UVec2, id: u32) {
+        self.objects[(texel.y * self.size.x + texel.x) as usize] = id;
+    }
+
+    pub fn weight(&self, texel: UVec2) -> VoronoiWeight {
+        self.weights[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    pub fn set_weight(&mut self, texel: UVec2, weight: VoronoiWeight) {
+        self.weights[(texel.y * self.size.x + texel.x) as usize] = weight;
+    }
+
+    fn get_clamped(&self, texel: IVec2) -> f32 {
+        self.get(clamp_texel(texel, self.size))
+    }
+
+    /// Center of `texel` moved along the alpha gradient to the estimated `0.5` crossing,
+    /// `None` when the gradient vanishes.
+    fn subpixel_crossing(&self, texel: UVec2) -> Option<Vec2> {
+        let gradient = self.gradient(texel).try_normalize()?;
+        Some(texel.as_vec2() + 0.5 + gradient * edge_distance(gradient, self.get(texel)))
+    }
+
+    /// Sobel gradient of the alpha around `texel`, pointing inside.
+    fn gradient(&self, texel: UVec2) -> Vec2 {
+        let at = |offset: IVec2| self.get_clamped(texel.as_ivec2() + offset);
+        let s = std::f32::consts::SQRT_2;
+        Vec2::new(
+            at(IVec2::new(1, -1)) + s * at(IVec2::X) + at(IVec2::ONE)
+                - at(IVec2::NEG_ONE)
+                - s * at(IVec2::NEG_X)
+                - at(IVec2::new(-1, 1)),
+            at(IVec2::new(-1, 1)) + s * at(IVec2::Y) + at(IVec2::ONE)
+                - at(IVec2::NEG_ONE)
+                - s * at(IVec2::NEG_Y)
+                - at(IVec2::new(1, -1)),
+        )
+    }
+
+    /// Rasterizes a triangle list mesh with `material` like `mask.wgsl` does: the alpha
+    /// mask is sampled at the transformed UVs with nearest filtering, missing images count
+    /// as opaque white, and fragments without coverage are discarded. `clip_from_local`
+    /// maps mesh positions to clip space. The id of the material defaults to `0`.
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &Mesh,
+        clip_from_local: Mat4,
+        material: &VoronoiMaterial,
+        images: &Assets<Image>,
+    ) {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            warn!("Only triangle lists can be rasterized into an AlphaMask");
+            return;
+        }
+
+        let Some(positions) = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(VertexAttributeValues::as_float3)
+        else {
+            return;
+        };
+
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+            _ => None,
+        };
+
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(indices) => indices.iter().collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let size = self.size.as_vec2();
+        let vertices: Vec<(Vec2, Vec2)> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, position)| {
+                let clip = clip_from_local * Vec3::from(*position).extend(1.0);
+                let ndc = clip.truncate().truncate() / clip.w;
+                let fragment = Vec2::new(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * size;
+                let uv = uvs.map_or(Vec2::ZERO, |uvs| Vec2::from(uvs[i]));
+                (fragment, material.uv_transform.transform_point2(uv))
+            })
+            .collect();
+
+        for triangle in indices.chunks_exact(3) {
+            self.draw_triangle(
+                [
+                    vertices[triangle[0]],
+                    vertices[triangle[1]],
+                    vertices[triangle[2]],
+                ],
+                material,
+                images.get(&material.alpha_mask),
+            );
+        }
+    }
+
+    fn draw_triangle(
+        &mut self,
+        vertices: [(Vec2, Vec2); 3],
+        material: &VoronoiMaterial,
+        alpha_mask: Option<&Image>,
+    ) {
+        let [(a, uv_a), (b, uv_b), (c, uv_c)] = vertices;
+
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+
+        let uv_at = |p: Vec2| {
+            let w = Vec3::new(edge(b, c, p), edge(c, a, p), edge(a, b, p)) / area;
+            uv_a * w.x + uv_b * w.y + uv_c * w.z
+        };
+        let coverage_at = |uv: Vec2| {
+            let color = alpha_mask.map_or(LinearRgba::WHITE, |image| sample_color(image, uv));
+            let coverage = material.channel.read(color);
+            if material.invert {
+                1.0 - coverage
+            } else {
+                coverage
+            }
+        };
+
+        let min = a.min(b).min(c).floor().max(Vec2::ZERO).as_uvec2();
+        let max = a.max(b).max(c).ceil().min(self.size.as_vec2()).as_uvec2();
+
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let p = Vec2::new(x as f32, y as f32) + 0.5;
+                let w = Vec3::new(edge(b, c, p), edge(c, a, p), edge(a, b, p)) / area;
+                if w.min_element() < 0.0 {
+                    continue;
+                }
+
+                let coverage = coverage_at(uv_at(p));
+
+                // Anti-aliased step across the threshold, with the screen space
+                // derivatives taken from the neighbouring pixels
+                let alpha = match material.threshold {
+                    Some(threshold) => {
+                        let fwidth = (coverage_at(uv_at(p + Vec2::X)) - coverage).abs()
+                            + (coverage_at(uv_at(p + Vec2::Y)) - coverage).abs();
+                        ((coverage - threshold) / fwidth.max(1e-4) + 0.5).clamp(0.0, 1.0)
+                    }
+                    None => coverage,
+                };
+
+                if alpha <= 0.0 {
+                    continue;
+                }
+
+                self.set(UVec2::new(x, y), alpha);
+                self.set_object(UVec2::new(x, y), material.id.unwrap_or_default());
+                self.set_weight(UVec2::new(x, y), material.weight);
+            }
+        }
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b - a).perp_dot(p - a)
+}
+
+fn sample_color(image: &Image, uv: Vec2) -> LinearRgba {
+    let size = image.size();
+    let texel = clamp_texel((uv * size.as_vec2()).floor().as_ivec2(), size);
+    image
+        .get_color_at(texel.x, texel.y)
+        .map_or(LinearRgba::WHITE, |color| color.to_linear())
+}
+
+/// Distance from a pixel center to the `0.5` alpha crossing along the normalized alpha
+/// `gradient`, assuming a straight edge through the pixel. Positive when the center is
+/// outside.
+fn edge_distance(gradient: Vec2, alpha: f32) -> f32 {
+    if gradient.x == 0.0 || gradient.y == 0.0 {
+        return 0.5 - alpha;
+    }
+
+    let g = gradient.abs();
+    let (gx, gy) = (g.max_element(), g.min_element());
+    let a1 = 0.5 * gy / gx;
+
+    if alpha < a1 {
+        0.5 * (gx + gy) - (2.0 * gx * gy * alpha).sqrt()
+    } else if alpha < 1.0 - a1 {
+        (0.5 - alpha) * gx
+    } else {
+        -0.5 * (gx + gy) + (2.0 * gx * gy * (1.0 - alpha)).sqrt()
+    }
+}
+
+/// Second seed texel layout, mirroring `pack_second_seed` in `flood_functions.wgsl`: the
+/// seed position as two `f16` in `x`, the object id and weight bits in `yzw`.
+fn pack_second_seed(seed: Vec2, id: u32, weight: VoronoiWeight) -> UVec4 {
+    let x = f16::from_f32(seed.x).to_bits() as u32;
+    let y = f16::from_f32(seed.y).to_bits() as u32;
+    UVec4::new(
+        x | (y << 16),
+        id,
+        weight.multiplicative.to_bits(),
+        weight.additive.to_bits(),
+    )
+}
+
+fn unpack_second_seed(second: UVec4) -> (Vec2, u32, VoronoiWeight) {
+    let seed = Vec2::new(
+        f16::from_bits(second.x as u16).to_f32(),
+        f16::from_bits((second.x >> 16) as u16).to_f32(),
+    );
+    let weight = VoronoiWeight {
+        multiplicative: f32::from_bits(second.z),
+        additive: f32::from_bits(second.w),
+    };
+    (seed, second.y, weight)
+}
+
+fn clamp_texel(texel: IVec2, size: UVec2) -> UVec2 {
+    texel.clamp(IVec2::ZERO, size.as_ivec2() - 1).as_uvec2()
+}
+
+/// Jump flood output with the same channel layout as `VoronoiTexture`: the nearest
+/// seed fragment coordinate in `xy` (`-1` when unknown), `1.0` in `z` for original
+/// seeds (`-1.0` when no seed lies within the maximum distance) and the alpha mask in
+/// `w`, plus the object id and weight owning each nearest seed.
+#[derive(Clone, Debug)]
+pub struct VoronoiField {
+    size: UVec2,
+    texels: Vec<Vec4>,
+    objects: Vec<u32>,
+    weights: Vec<VoronoiWeight>,
+    max_distance: Option<f32>,
+    metric: DistanceMetric,
+    seed_mode: SeedMode,
+    /// Second seed texels packed by [`pack_second_seed`], with
+    /// [`VoronoiField::with_second_seed`].
+    second_seeds: Option<Vec<UVec4>>,
+}
+
+impl VoronoiField {
+    /// Runs the seed pass followed by every flood step of the default schedule, like
+    /// `FloodDrawNode`.
+    pub fn from_mask(mask: &AlphaMask) -> Self {
+        let mut field = Self::seed(mask);
+        field.jump_flood(JfaSchedule::default());
+        field
+    }
+
+    /// Runs every flood step of `schedule`, capped to the maximum distance if one is set,
+    /// see [`JfaSchedule::with_max_distance`].
+    pub fn jump_flood(&mut self, schedule: JfaSchedule) {
+        for step in schedule
+            .with_max_distance(self.max_distance)
+            .steps(self.size)
+        {
+            self.flood(step);
+        }
+    }
+
+    /// Marks edge pixels of the mask as seeds, mirroring `flood_seed.wgsl`.
+    pub fn seed(mask: &AlphaMask) -> Self {
+        let size = mask.size();
+        let offsets = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+        let mut texels = Vec::with_capacity((size.x * size.y) as usize);
+        let mut objects = Vec::with_capacity((size.x * size.y) as usize);
+        let mut weights = Vec::with_capacity((size.x * size.y) as usize);
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let texel = UVec2::new(x, y);
+                let alpha = mask.get(texel);
+
+                let is_edge = alpha == 1.0
+                    && offsets
+                        .iter()
+                        .any(|offset| mask.get_clamped(texel.as_ivec2() + *offset) != alpha);
+
+                if is_edge {
+                    texels.push((texel.as_vec2() + 0.5).extend(1.0).extend(alpha));
+                    objects.push(mask.object(texel));
+                    weights.push(mask.weight(texel));
+                } else {
+                    texels.push(NULL_SEED.extend(0.0).extend(alpha));
+                    objects.push(0);
+                    weights.push(VoronoiWeight::default());
+                }
+            }
+        }
+
+        Self {
+            size,
+            texels,
+            objects,
+            weights,
+            max_distance: None,
+            metric: DistanceMetric::default(),
+            seed_mode: SeedMode::Edge,
+            second_seeds: None,
+        }
+    }
+
+    /// Compares seeds with `metric` in the following flood steps and distance queries,
+    /// like `VoronoiCamera::metric`.
+    pub fn with_metric(mut self, metric: DistanceMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Seed mode the field was seeded with, deciding which pixels are inside in
+    /// [`VoronoiField::signed_distance`]. Set by the seed functions, defaults to
+    /// [`SeedMode::Edge`] for fields built with [`VoronoiField::from_texels`].
+    pub fn with_seed_mode(mut self, seed_mode: SeedMode) -> Self {
+        self.seed_mode = seed_mode;
+        self
+    }
+
+    /// Also tracks the nearest seed of another object than the nearest seed in the
+    /// following flood steps, like `VoronoiCamera::second_seed`.
+    pub fn with_second_seed(mut self) -> Self {
+        let null_second_seed = pack_second_seed(NULL_SEED, 0, VoronoiWeight::default());
+        self.second_seeds = Some(vec![null_second_seed; self.texels.len()]);
+        self
+    }
+
+    /// Ignores seeds further than `max_distance` texels in the following flood steps,
+    /// like `VoronoiCamera::max_distance`.
+    pub fn with_max_distance(mut self, max_distance: Option<f32>) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Marks pixels on both sides of the `0.5` alpha crossing as seeds placed at the
+    /// crossing estimated by the inside pixel, mirroring `flood_seed.wgsl` with
+    /// `SeedMode::TwoSided`.
+    pub fn seed_two_sided(mask: &AlphaMask) -> Self {
+        let size = mask.size();
+        let offsets = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+        let mut texels = Vec::with_capacity((size.x * size.y) as usize);
+        let mut objects = Vec::with_capacity((size.x * size.y) as usize);
+        let mut weights = Vec::with_capacity((size.x * size.y) as usize);
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let texel = UVec2::new(x, y);
+                let alpha = mask.get(texel);
+                let inside = alpha >= 0.5;
+
+                let mut is_edge = false;
+                let mut direction = Vec2::ZERO;
+                let mut inside_texel = texel;
+
+                for offset in offsets {
+                    let neighbour = clamp_texel(texel.as_ivec2() + offset, size);
+                    if (mask.get(neighbour) >= 0.5) != inside {
+                        is_edge = true;
+                        direction += offset.as_vec2();
+                        if !inside {
+                            inside_texel = neighbour;
+                        }
+                    }
+                }
+
+                if is_edge {
+                    // Both sides use the crossing estimated by the inside pixel, or move
+                    // halfway towards the other side when the gradient vanishes
+                    let position = mask.subpixel_crossing(inside_texel).unwrap_or_else(|| {
+                        texel.as_vec2() + 0.5 + 0.5 * direction.normalize_or_zero()
+                    });
+                    texels.push(position.extend(1.0).extend(alpha));
+                    objects.push(mask.object(inside_texel));
+                    weights.push(mask.weight(inside_texel));
+                } else {
+                    texels.push(NULL_SEED.extend(0.0).extend(alpha));
+                    objects.push(0);
+                    weights.push(VoronoiWeight::default());
+                }
+            }
+        }
+
+        Self {
+            size,
+            texels,
+            objects,
+            weights,
+            max_distance: None,
+            metric: DistanceMetric::default(),
+            seed_mode: SeedMode::TwoSided,
+            second_seeds: None,
+        }
+    }
+
+    /// Marks partially covered pixels and hard edges as seeds placed at the estimated
+    /// `0.5` alpha crossing, mirroring `flood_seed.wgsl` with `SeedMode::Subpixel`.
+    pub fn seed_subpixel(mask: &AlphaMask) -> Self {
+        let size = mask.size();
+        let offsets = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+        let mut texels = Vec::with_capacity((size.x * size.y) as usize);
+        let mut objects = Vec::with_capacity((size.x * size.y) as usize);
+        let mut weights = Vec::with_capacity((size.x * size.y) as usize);
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let texel = UVec2::new(x, y);
+                let alpha = mask.get(texel);
+                let is_edge = (alpha > 0.0 && alpha < 1.0)
+                    || (alpha == 1.0
+                        && offsets
+                            .iter()
+                            .any(|offset| mask.get_clamped(texel.as_ivec2() + *offset) == 0.0));
+
+                if !is_edge {
+                    texels.push(NULL_SEED.extend(0.0).extend(alpha));
+                    objects.push(0);
+                    weights.push(VoronoiWeight::default());
+                    continue;
+                }
+
+                let position = mask
+                    .subpixel_crossing(texel)
+                    .unwrap_or(texel.as_vec2() + 0.5);
+                texels.push(position.extend(1.0).extend(alpha));
+                objects.push(mask.object(texel));
+                weights.push(mask.weight(texel));
+            }
+        }
+
+        Self {
+            size,
+            texels,
+            objects,
+            weights,
+            max_distance: None,
+            metric: DistanceMetric::default(),
+            seed_mode: SeedMode::Subpixel,
+            second_seeds: None,
+        }
+    }
+
+    /// Marks the pixel containing the fragment coordinate `position` as a seed, mirroring
+    /// `point_seed.wgsl`. Positions outside of the field are ignored.
+    pub fn add_seed(&mut self, position: Vec2, id: u32, weight: VoronoiWeight) {
+        if position.cmplt(Vec2::ZERO).any() || position.cmpge(self.size.as_vec2()).any() {
+            return;
+        }
+
+        let texel = position.as_uvec2();
+        let index = (texel.y * self.size.x + texel.x) as usize;
+        let alpha = self.texels[index].w;
+
+        self.texels[index] = (texel.as_vec2() + 0.5).extend(1.0).extend(alpha);
+        self.objects[index] = id;
+        self.weights[index] = weight;
+    }
+
+    /// Runs a single jump flood step, mirroring `flood.wgsl`.
+    pub fn flood(&mut self, step: UVec2) {
+        if self.second_seeds.is_some() {
+            self.flood_second(step);
+            return;
+        }
+
+        let mut output = Vec::with_capacity(self.texels.len());
+        let mut output_objects = Vec::with_capacity(self.objects.len());
+        let mut output_weights = Vec::with_capacity(self.weights.len());
+
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let texel = UVec2::new(x, y);
+                let original_seed = self.get(texel);
+                let original_object = self.object(texel);
+                let original_weight = self.weight(texel);
+
+                if original_seed.z == 1.0 {
+                    output.push(original_seed);
+                    output_objects.push(original_object);
+                    output_weights.push(original_weight);
+                    continue;
+                }
+
+                let position = texel.as_vec2() + 0.5;
+                let mut current_seed = original_seed.truncate().truncate();
+                let mut current_object = original_object;
+                let mut current_weight = original_weight;
+                let mut current_dist = f32::MAX;
+                let mut current_weighted_dist = f32::MAX;
+                let max_distance = self.max_distance.unwrap_or(f32::MAX);
+
+                for offset_x in -1..=1 {
+                    for offset_y in -1..=1 {
+                        let neighbour = clamp_texel(
+                            texel.as_ivec2() + IVec2::new(offset_x, offset_y) * step.as_ivec2(),
+                            self.size,
+                        );
+                        let neighbour_seed = self.get(neighbour).truncate().truncate();
+                        let neighbour_weight = self.weight(neighbour);
+                        let neighbour_dist = self.metric.distance(position, neighbour_seed);
+                        let neighbour_weighted_dist =
+                            neighbour_weight.weighted_distance(neighbour_dist);
+
+                        if neighbour_seed.cmpge(Vec2::ZERO).all()
+                            && neighbour_dist <= max_distance
+                            && neighbour_weighted_dist < current_weighted_dist
+                        {
+                            current_seed = neighbour_seed;
+                            current_object = self.object(neighbour);
+                            current_weight = neighbour_weight;
+                            current_dist = neighbour_dist;
+                            current_weighted_dist = neighbour_weighted_dist;
+                        }
+                    }
+                }
+
+                if current_dist > max_distance {
+                    output.push(NULL_SEED.extend(-1.0).extend(original_seed.w));
+                } else {
+                    output.push(current_seed.extend(0.0).extend(original_seed.w));
+                }
+                output_objects.push(current_object);
+                output_weights.push(current_weight);
+            }
+        }
+
+        self.texels = output;
+        self.objects = output_objects;
+        self.weights = output_weights;
+    }
+
+    /// Runs a single jump flood step also keeping the nearest seed of another object,
+    /// mirroring `flood_texel_second` in `flood_functions.wgsl`.
+    fn flood_second(&mut self, step: UVec2) {
+        let Some(second_seeds) = &self.second_seeds else {
+            return;
+        };
+
+        let mut output = Vec::with_capacity(self.texels.len());
+        let mut output_objects = Vec::with_capacity(self.objects.len());
+        let mut output_weights = Vec::with_capacity(self.weights.len());
+        let mut output_second_seeds = Vec::with_capacity(second_seeds.len());
+        let max_distance = self.max_distance.unwrap_or(f32::MAX);
+
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let texel = UVec2::new(x, y);
+                let original_seed = self.get(texel);
+                let original_object = self.object(texel);
+                let original_weight = self.weight(texel);
+                let is_seed = original_seed.z == 1.0;
+                let position = texel.as_vec2() + 0.5;
+
+                let mut nearest = (NULL_SEED, 0, VoronoiWeight::default());
+                let mut nearest_dist = f32::MAX;
+                let mut nearest_weighted_dist = f32::MAX;
+                if is_seed {
+                    nearest = (
+                        original_seed.truncate().truncate(),
+                        original_object,
+                        original_weight,
+                    );
+                    nearest_dist = 0.0;
+                    nearest_weighted_dist = -f32::MAX;
+                }
+
+                let mut second = (NULL_SEED, 0, VoronoiWeight::default());
+                let mut second_weighted_dist = f32::MAX;
+
+                for offset_x in -1..=1 {
+                    for offset_y in -1..=1 {
+                        let neighbour = clamp_texel(
+                            texel.as_ivec2() + IVec2::new(offset_x, offset_y) * step.as_ivec2(),
+                            self.size,
+                        );
+                        let neighbour_index = (neighbour.y * self.size.x + neighbour.x) as usize;
+                        let candidates = [
+                            (
+                                self.get(neighbour).truncate().truncate(),
+                                self.object(neighbour),
+                                self.weight(neighbour),
+                            ),
+                            unpack_second_seed(second_seeds[neighbour_index]),
+                        ];
+
+                        for candidate in candidates {
+                            let (candidate_seed, candidate_object, candidate_weight) = candidate;
+                            let candidate_dist = self.metric.distance(position, candidate_seed);
+                            if candidate_seed.cmplt(Vec2::ZERO).any()
+                                || candidate_dist > max_distance
+                            {
+                                continue;
+                            }
+
+                            let candidate_weighted_dist =
+                                candidate_weight.weighted_distance(candidate_dist);
+                            let other_object = candidate_object != nearest.1;
+
+                            if candidate_weighted_dist < nearest_weighted_dist {
+                                if other_object {
+                                    second = nearest;
+                                    second_weighted_dist = nearest_weighted_dist;
+                                }
+                                nearest = candidate;
+                                nearest_dist = candidate_dist;
+                                nearest_weighted_dist = candidate_weighted_dist;
+                            } else if other_object && candidate_weighted_dist < second_weighted_dist
+                            {
+                                second = candidate;
+                                second_weighted_dist = candidate_weighted_dist;
+                            }
+                        }
+                    }
+                }
+
+                if is_seed {
+                    output.push(original_seed);
+                    output_objects.push(original_object);
+                    output_weights.push(original_weight);
+                } else if nearest_dist > max_distance {
+                    // Far texels keep their own object, like `flood`
+                    output.push(NULL_SEED.extend(-1.0).extend(original_seed.w));
+                    output_objects.push(original_object);
+                    output_weights.push(original_weight);
+                } else {
+                    output.push(nearest.0.extend(0.0).extend(original_seed.w));
+                    output_objects.push(nearest.1);
+                    output_weights.push(nearest.2);
+                }
+                output_second_seeds.push(pack_second_seed(second.0, second.1, second.2));
+            }
+        }
+
+        self.texels = output;
+        self.objects = output_objects;
+        self.weights = output_weights;
+        self.second_seeds = Some(output_second_seeds);
+    }
+
+    /// Wraps texels that are already in the `VoronoiTexture` layout, in row-major order.
+    /// Fields created this way carry no object ids or weights.
+    pub fn from_texels(size: UVec2, texels: Vec<Vec4>) -> Self {
+        assert_eq!(texels.len(), (size.x * size.y) as usize);
+        Self {
+            size,
+            texels,
+            objects: Vec::new(),
+            weights: Vec::new(),
+            max_distance: None,
+            metric: DistanceMetric::default(),
+            seed_mode: SeedMode::Edge,
+            second_seeds: None,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
+    pub fn seed_mode(&self) -> SeedMode {
+        self.seed_mode
+    }
+
+    pub fn texels(&self) -> &[Vec4] {
+        &self.texels
+    }
+
+    pub fn get(&self, texel: UVec2) -> Vec4 {
+        self.texels[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    fn object(&self, texel: UVec2) -> u32 {
+        self.objects[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    fn weight(&self, texel: UVec2) -> VoronoiWeight {
+        self.weights[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    /// Object id owning the nearest seed, if any was found.
+    pub fn nearest_object(&self, texel: UVec2) -> Option<u32> {
+        self.nearest_seed(texel)?;
+        self.objects
+            .get((texel.y * self.size.x + texel.x) as usize)
+            .copied()
+    }
+
+    /// Fragment coordinate of the nearest seed, if any was found within the maximum
+    /// distance.
+    pub fn nearest_seed(&self, texel: UVec2) -> Option<Vec2> {
+        let seed = self.get(texel).truncate().truncate();
+        seed.cmpge(Vec2::ZERO).all().then_some(seed)
+    }
+
+    /// Fragment coordinate of the nearest seed of another object than the nearest seed,
+    /// with [`VoronoiField::with_second_seed`]. Stored as `f16` like on the GPU.
+    pub fn second_seed(&self, texel: UVec2) -> Option<Vec2> {
+        let (seed, _, _) = unpack_second_seed(self.packed_second_seed(texel)?);
+        seed.cmpge(Vec2::ZERO).all().then_some(seed)
+    }
+
+    /// Object id owning the second seed, see [`VoronoiField::second_seed`].
+    pub fn second_object(&self, texel: UVec2) -> Option<u32> {
+        self.second_seed(texel)?;
+        let (_, object, _) = unpack_second_seed(self.packed_second_seed(texel)?);
+        Some(object)
+    }
+
+    fn packed_second_seed(&self, texel: UVec2) -> Option<UVec4> {
+        let second_seeds = self.second_seeds.as_ref()?;
+        Some(second_seeds[(texel.y * self.size.x + texel.x) as usize])
+    }
+
+    /// Signed distance in texels to the border between the cells of the nearest and the
+    /// second seed, positive inside the cell of the nearest seed, mirroring
+    /// `cell_edge_distance` in `flood_functions.wgsl`.
+    pub fn cell_edge_distance(&self, texel: UVec2) -> Option<f32> {
+        let seed = self.nearest_seed(texel)?;
+        let second_seed = self.second_seed(texel)?;
+        let direction = second_seed - seed;
+        if direction == Vec2::ZERO {
+            return Some(0.0);
+        }
+        Some((0.5 * (seed + second_seed) - (texel.as_vec2() + 0.5)).dot(direction.normalize()))
+    }
+
+    /// Distance in texels to the nearest seed, negative inside the mask, see
+    /// [`SeedMode::is_inside`].
+    pub fn signed_distance(&self, texel: UVec2) -> Option<f32> {
+        let dist = self
+            .metric
+            .distance(texel.as_vec2() + 0.5, self.nearest_seed(texel)?);
+        Some(if self.seed_mode.is_inside(self.get(texel).w) {
+            -dist
+        } else {
+            dist
+        })
+    }
+
+    /// Copies the field into an `Rgba32Float` image.
+    pub fn to_image(&self) -> Image {
+        let data = self
+            .texels
+            .iter()
+            .flat_map(|texel| texel.to_array())
+            .flat_map(f32::to_le_bytes)
+            .collect();
+
+        Image::new(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba32Float,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::MaskChannel;
+
+    const SIZE: UVec2 = UVec2::splat(16);
+
+    /// Opaque square covering the texels from 4 to 11 on both axes.
+    fn square_mask() -> AlphaMask {
+        AlphaMask::from_fn(SIZE, |texel| {
+            if texel.cmpge(UVec2::splat(4)).all() && texel.cmplt(UVec2::splat(12)).all() {
+                1.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    /// Distance from the center of `texel` to the nearest edge seed of `mask`.
+    fn brute_force_distance(mask: &AlphaMask, texel: UVec2) -> f32 {
+        let seeds = VoronoiField::seed(mask);
+        (0..SIZE.x)
+            .flat_map(|x| (0..SIZE.y).map(move |y| UVec2::new(x, y)))
+            .filter(|seed| seeds.get(*seed).z == 1.0)
+            .map(|seed| (seed.as_vec2() + 0.5).distance(texel.as_vec2() + 0.5))
+            .fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn draw_mesh_covers_texel_centers_inside_the_mesh() {
+        let mut mask = AlphaMask::new(SIZE);
+        let mesh = Mesh::from(Rectangle::new(8.0, 8.0));
+        let clip_from_local = Mat4::orthographic_rh(-8.0, 8.0, -8.0, 8.0, -1.0, 1.0);
+        let material = VoronoiMaterial {
+            id: Some(7),
+            ..default()
+        };
+
+        mask.draw_mesh(&mesh, clip_from_local, &material, &Assets::default());
+
+        let expected = square_mask();
+        for y in 0..SIZE.y {
+            for x in 0..SIZE.x {
+                let texel = UVec2::new(x, y);
+                assert_eq!(mask.get(texel), expected.get(texel), "alpha at {texel}");
+                let id = if expected.get(texel) == 1.0 { 7 } else { 0 };
+                assert_eq!(mask.object(texel), id, "object at {texel}");
+            }
+        }
+    }
+
+    /// Single row `Rgba32Float` image of `colors`.
+    fn row_image(colors: &[LinearRgba]) -> Image {
+        Image::new(
+            Extent3d {
+                width: colors.len() as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            colors
+                .iter()
+                .flat_map(|color| color.to_f32_array())
+                .flat_map(f32::to_le_bytes)
+                .collect(),
+            TextureFormat::Rgba32Float,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    /// Alpha of row `8` after drawing a mesh covering the whole mask with `material`.
+    fn draw_full_mask(material: VoronoiMaterial, images: &Assets<Image>) -> Vec<f32> {
+        let mut mask = AlphaMask::new(SIZE);
+        let mesh = Mesh::from(Rectangle::new(16.0, 16.0));
+        let clip_from_local = Mat4::orthographic_rh(-8.0, 8.0, -8.0, 8.0, -1.0, 1.0);
+        mask.draw_mesh(&mesh, clip_from_local, &material, images);
+        (0..SIZE.x).map(|x| mask.get(UVec2::new(x, 8))).collect()
+    }
+
+    #[test]
+    fn mask_settings_select_the_coverage() {
+        let mut images = Assets::default();
+        // Red on the left half of the mask, opaque on the right half
+        let alpha_mask = images.add(row_image(&[
+            LinearRgba::new(1.0, 0.0, 0.0, 0.0),
+            LinearRgba::new(0.0, 0.0, 0.0, 1.0),
+        ]));
+        let left = |alpha| [[alpha; 8], [1.0 - alpha; 8]].concat();
+
+        let material = VoronoiMaterial::new(alpha_mask);
+        assert_eq!(draw_full_mask(material.clone(), &images), left(0.0));
+        assert_eq!(
+            draw_full_mask(material.clone().with_channel(MaskChannel::R), &images),
+            left(1.0)
+        );
+        assert_eq!(draw_full_mask(material.inverted(), &images), left(1.0));
+    }
+
+    #[test]
+    fn uv_transform_selects_the_sampled_region() {
+        let mut images = Assets::default();
+        let alpha_mask = images.add(row_image(&[
+            LinearRgba::new(0.0, 0.0, 0.0, 0.0),
+            LinearRgba::new(0.0, 0.0, 0.0, 1.0),
+        ]));
+
+        let material = VoronoiMaterial::new(alpha_mask).with_uv_rect(Rect::new(0.5, 0.0, 1.0, 1.0));
+        assert_eq!(draw_full_mask(material, &images), [1.0; 16]);
+    }
+
+    #[test]
+    fn threshold_steps_the_coverage_with_anti_aliasing() {
+        let mut images = Assets::default();
+        let alpha_mask = images.add(row_image(
+            &[0.1, 0.3, 0.7, 0.9].map(|alpha| LinearRgba::new(1.0, 1.0, 1.0, alpha)),
+        ));
+
+        let alpha = draw_full_mask(
+            VoronoiMaterial::new(alpha_mask).with_threshold(0.45),
+            &images,
+        );
+
+        // Only the pixel before the crossing sees the coverage change across it
+        let mut expected = [[0.0; 8], [1.0; 8]].concat();
+        expected[7] = (0.3 - 0.45) / 0.4 + 0.5;
+        for (x, (alpha, expected)) in alpha.iter().zip(expected).enumerate() {
+            assert!((alpha - expected).abs() < 1e-5, "alpha {alpha} at {x}");
+        }
+    }
+
+    #[test]
+    fn seed_marks_the_inner_edge_of_the_mask() {
+        let field = VoronoiField::seed(&square_mask());
+
+        for y in 0..SIZE.y {
+            for x in 0..SIZE.x {
+                let texel = UVec2::new(x, y);
+                let inside =
+                    texel.cmpge(UVec2::splat(4)).all() && texel.cmplt(UVec2::splat(12)).all();
+                let interior =
+                    texel.cmpge(UVec2::splat(5)).all() && texel.cmplt(UVec2::splat(11)).all();
+                let is_seed = inside && !interior;
+
+                let value = field.get(texel);
+                assert_eq!(value.z == 1.0, is_seed, "seed at {texel}");
+                assert_eq!(value.w, if inside { 1.0 } else { 0.0 });
+                if is_seed {
+                    assert_eq!(field.nearest_seed(texel), Some(texel.as_vec2() + 0.5));
+                } else {
+                    assert_eq!(field.nearest_seed(texel), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jfa_schedules_halve_the_step_down_to_one() {
+        let steps = |schedule: JfaSchedule| -> Vec<u32> {
+            schedule
+                .steps(SIZE)
+                .into_iter()
+                .map(|step| step.x)
+                .collect()
+        };
+
+        assert_eq!(steps(JfaSchedule::JfaPlusOne), [8, 4, 2, 1, 1]);
+        assert_eq!(steps(JfaSchedule::OnePlusJfa), [1, 8, 4, 2, 1]);
+        assert_eq!(steps(JfaSchedule::JfaPlusTwo), [8, 4, 2, 1, 2, 1]);
+        assert_eq!(steps(JfaSchedule::JfaSquared), [8, 4, 2, 1, 8, 4, 2, 1]);
+        assert_eq!(steps(JfaSchedule::Capped { max_step: 3 }), [4, 2, 1, 1]);
+        assert_eq!(
+            steps(JfaSchedule::Capped { max_step: 100 }),
+            [8, 4, 2, 1, 1]
+        );
+        assert_eq!(
+            steps(JfaSchedule::JfaSquared.with_max_distance(Some(2.5))),
+            [4, 2, 1, 1]
+        );
+        assert_eq!(
+            steps(JfaSchedule::JfaSquared.with_max_distance(None)),
+            [8, 4, 2, 1, 8, 4, 2, 1]
+        );
+    }
+
+    #[test]
+    fn signed_distance_matches_the_exact_distance_to_the_edge() {
+        let mask = square_mask();
+        let field = VoronoiField::from_mask(&mask);
+
+        for y in 0..SIZE.y {
+            for x in 0..SIZE.x {
+                let texel = UVec2::new(x, y);
+                let distance = brute_force_distance(&mask, texel);
+                let expected = if mask.get(texel) > 0.0 {
+                    -distance
+                } else {
+                    distance
+                };
+                let signed_distance = field.signed_distance(texel).unwrap();
+                assert!(
+                    (signed_distance - expected).abs() < 1e-4,
+                    "{signed_distance} != {expected} at {texel}"
+                );
+            }
+        }
+
+        // Known distances to the left edge, whose seeds are centered on x = 4.5
+        assert_eq!(field.signed_distance(UVec2::new(1, 8)), Some(3.0));
+        assert_eq!(field.signed_distance(UVec2::new(4, 8)), Some(-0.0));
+        assert_eq!(field.signed_distance(UVec2::new(6, 8)), Some(-2.0));
+    }
+
+    #[test]
+    fn two_sided_seeds_meet_at_the_subpixel_crossing() {
+        // Vertical edge at x = 4.25, the pixels of column 4 being three quarters covered
+        let mask = AlphaMask::from_fn(SIZE, |texel| match texel.x {
+            0..4 => 0.0,
+            4 => 0.75,
+            _ => 1.0,
+        });
+        let field = VoronoiField::seed_two_sided(&mask);
+
+        for y in 0..SIZE.y {
+            let crossing = Vec2::new(4.25, y as f32 + 0.5);
+            assert_eq!(field.nearest_seed(UVec2::new(3, y)), Some(crossing));
+            assert_eq!(field.nearest_seed(UVec2::new(4, y)), Some(crossing));
+            assert_eq!(field.get(UVec2::new(5, y)).z, 0.0);
+        }
+    }
+
+    #[test]
+    fn inside_depends_on_the_seed_mode() {
+        let mask = AlphaMask::from_fn(SIZE, |texel| if texel.x < 8 { 0.25 } else { 1.0 });
+        let texel = UVec2::new(2, 8);
+
+        let edge = VoronoiField::from_mask(&mask);
+        assert!(edge.signed_distance(texel).unwrap() < 0.0);
+
+        let mut subpixel = VoronoiField::seed_subpixel(&mask);
+        subpixel.jump_flood(JfaSchedule::default());
+        assert!(subpixel.signed_distance(texel).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn second_seeds_round_trip_through_the_packed_layout() {
+        let weight = VoronoiWeight {
+            multiplicative: 2.0,
+            additive: -1.5,
+        };
+        let packed = pack_second_seed(Vec2::new(12.5, 300.5), 7, weight);
+        assert_eq!(
+            unpack_second_seed(packed),
+            (Vec2::new(12.5, 300.5), 7, weight)
+        );
+
+        let (null_seed, _, _) =
+            unpack_second_seed(pack_second_seed(NULL_SEED, 0, VoronoiWeight::default()));
+        assert_eq!(null_seed, NULL_SEED);
+    }
+
+    #[test]
+    fn second_seeds_belong_to_another_object() {
+        // Two objects, one on each side of the field
+        let mut mask = AlphaMask::from_fn(SIZE, |texel| {
+            if (2..5).contains(&texel.x) || (11..14).contains(&texel.x) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        for x in 8..SIZE.x {
+            for y in 0..SIZE.y {
+                mask.set_object(UVec2::new(x, y), 1);
+            }
+        }
+
+        let mut field = VoronoiField::seed(&mask).with_second_seed();
+        field.jump_flood(JfaSchedule::default());
+
+        for x in 0..SIZE.x {
+            for y in 0..SIZE.y {
+                let texel = UVec2::new(x, y);
+                let object = field.nearest_object(texel).unwrap();
+                assert_eq!(field.second_object(texel), Some(1 - object));
+
+                let second_seed = field.second_seed(texel).unwrap();
+                assert_eq!(second_seed.x < 8.0, object == 1);
+                assert!(field.cell_edge_distance(texel).unwrap() >= 0.0);
+            }
+        }
+
+        // The cells meet halfway between the inner edges at x = 4.5 and x = 11.5
+        assert!((field.cell_edge_distance(UVec2::new(7, 8)).unwrap() - 0.5).abs() < 1e-4);
+        assert!((field.cell_edge_distance(UVec2::new(8, 8)).unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    /// First texel of row `8` owned by the right of two seeds at `x = 2.5` and
+    /// `x = 13.5`, the left one weighted by `weight`.
+    fn weighted_cell_boundary(weight: VoronoiWeight) -> u32 {
+        let mut field = VoronoiField::seed(&AlphaMask::new(SIZE));
+        field.add_seed(Vec2::new(2.5, 8.5), 0, weight);
+        field.add_seed(Vec2::new(13.5, 8.5), 1, VoronoiWeight::default());
+        field.jump_flood(JfaSchedule::default());
+
+        (0..SIZE.x)
+            .find(|x| field.nearest_object(UVec2::new(*x, 8)) == Some(1))
+            .unwrap()
+    }
+
+    #[test]
+    fn weights_move_the_cell_boundary() {
+        // Unweighted, the cells meet halfway at x = 8
+        assert_eq!(weighted_cell_boundary(VoronoiWeight::default()), 8);
+        // x - 2.5 < 2 (13.5 - x) up to x = 9.83
+        assert_eq!(
+            weighted_cell_boundary(VoronoiWeight::multiplicative(2.0)),
+            10
+        );
+        // (x - 2.5)² - 44 < (13.5 - x)² up to x = 10
+        assert_eq!(weighted_cell_boundary(VoronoiWeight::additive(44.0)), 10);
+        // Negative additive weights shrink the cell, up to x = 6
+        assert_eq!(weighted_cell_boundary(VoronoiWeight::additive(-44.0)), 6);
+    }
+
+    #[test]
+    fn max_distance_marks_far_texels() {
+        let mut field = VoronoiField::seed(&square_mask()).with_max_distance(Some(2.0));
+        field.jump_flood(JfaSchedule::default());
+
+        assert_eq!(field.signed_distance(UVec2::new(2, 8)), Some(2.0));
+        assert_eq!(field.get(UVec2::new(0, 8)).z, -1.0);
+        assert_eq!(field.signed_distance(UVec2::new(0, 8)), None);
+    }
+}
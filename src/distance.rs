@@ -0,0 +1,186 @@
+use std::iter;
+
+use bevy::{
+    asset::weak_handle,
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        render_resource::{
+            binding_types::{texture_2d, uniform_buffer},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, FragmentState, MultisampleState, Operations,
+            PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, ShaderStages, ShaderType, SpecializedRenderPipeline,
+            TextureFormat, TextureSampleType, UniformBuffer,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::CachedTexture,
+    },
+};
+
+use crate::{
+    flood::SeedMode,
+    plugin::{VoronoiCamera, VoronoiTexture},
+};
+
+pub const DISTANCE_SHADER: Handle<Shader> = weak_handle!("b7d2f4a9-3c61-4e85-8f0a-5d9c1e6b2a47");
+
+/// Largest finite value of the `R16Float` distance texture.
+const HALF_MAX: f32 = 65504.0;
+
+/// Signed distance to the nearest seed in world units, resolved from the final flood
+/// output when [`VoronoiCamera::distance_field`] is set. Negative inside the mask,
+/// texels without a seed in range hold the largest representable distance.
+#[derive(Clone, Component)]
+pub struct VoronoiDistanceTexture {
+    pub texture: CachedTexture,
+}
+
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct DistanceUniform {
+    /// Size of a texel of the `VoronoiTexture` in world units.
+    pub world_per_texel: Vec2,
+    pub minkowski_p: f32,
+    /// Distance written to texels without a seed in range.
+    pub far_distance: f32,
+}
+
+impl DistanceUniform {
+    /// Settings for a field of `size` texels seeded with the `clip_from_world` projection,
+    /// see [`VoronoiFloodProgress`](crate::prelude::VoronoiFloodProgress).
+    pub fn new(clip_from_world: Mat4, voronoi_camera: &VoronoiCamera, size: UVec2) -> Self {
+        let world_from_clip = clip_from_world.inverse();
+
+        // A texel spans 2 / size in clip space
+        let world_per_texel = Vec2::new(
+            world_from_clip.x_axis.truncate().truncate().length(),
+            world_from_clip.y_axis.truncate().truncate().length(),
+        ) * 2.0
+            / size.as_vec2();
+
+        Self {
+            world_per_texel,
+            minkowski_p: voronoi_camera.metric.minkowski_p(),
+            far_distance: voronoi_camera
+                .max_distance
+                .map_or(HALF_MAX, |max_distance| {
+                    max_distance * world_per_texel.max_element()
+                }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DistancePipelineKey {
+    pub metric_shader_def: &'static str,
+    /// Selects which pixels are inside, see [`SeedMode::is_inside`].
+    pub seed_mode: SeedMode,
+}
+
+#[derive(Resource)]
+pub struct DistancePipeline {
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for DistancePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "distance_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    uniform_buffer::<DistanceUniform>(false),
+                ),
+            ),
+        );
+
+        Self { layout }
+    }
+}
+
+impl SpecializedRenderPipeline for DistancePipeline {
+    type Key = DistancePipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("distance_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: DISTANCE_SHADER,
+                shader_defs: iter::once(key.metric_shader_def)
+                    .chain(key.seed_mode.shader_def())
+                    .map(Into::into)
+                    .collect(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: TextureFormat::R16Float,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            push_constant_ranges: vec![],
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+}
+
+/// Resolves the final flood output in `voronoi_textures.result()` into `distance_texture`.
+pub fn run_distance_pass<'w>(
+    world: &'w World,
+    render_context: &mut RenderContext<'w>,
+    voronoi_textures: &VoronoiTexture,
+    distance_texture: &VoronoiDistanceTexture,
+    pipeline: CachedRenderPipelineId,
+    settings: DistanceUniform,
+) {
+    let distance_pipeline = world.resource::<DistancePipeline>();
+
+    let Some(result) = voronoi_textures.result() else {
+        return;
+    };
+
+    let mut settings = UniformBuffer::from(settings);
+
+    settings.write_buffer(
+        render_context.render_device(),
+        world.resource::<RenderQueue>(),
+    );
+
+    let (Some(pipeline), Some(settings)) = (
+        world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline),
+        settings.binding(),
+    ) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "distance_bind_group",
+        &distance_pipeline.layout,
+        &BindGroupEntries::sequential((&result.default_view, settings)),
+    );
+
+    let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("distance_pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: &distance_texture.texture.default_view,
+            resolve_target: None,
+            ops: Operations::default(),
+        })],
+        ..default()
+    });
+
+    pass.set_render_pipeline(pipeline);
+    pass.set_bind_group(0, &bind_group, &[]);
+    pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,35 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import bevy_voronoi::flood::metric_distance
+
+struct DistanceUniform {
+    world_per_texel: vec2<f32>,
+    minkowski_p: f32,
+    far_distance: f32,
+}
+
+@group(0) @binding(0) var seed_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> settings: DistanceUniform;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let seed = textureLoad(seed_texture, vec2<i32>(in.position.xy), 0);
+
+    var dist = settings.far_distance;
+    if seed.x >= 0. && seed.y >= 0. {
+        dist = metric_distance(
+            in.position.xy * settings.world_per_texel,
+            seed.xy * settings.world_per_texel,
+            settings.minkowski_p,
+        );
+    }
+
+    // Negative inside the mask, see `SeedMode::is_inside`
+#ifdef SEED_TWO_SIDED
+    let inside = seed.w >= 0.5;
+#else ifdef SEED_SUBPIXEL
+    let inside = seed.w >= 0.5;
+#else
+    let inside = seed.w > 0.;
+#endif
+    return vec4(select(dist, -dist, inside), 0.0, 0.0, 0.0);
+}
@@ -1,135 +1,591 @@
+use std::iter;
+
 use bevy::{
     asset::weak_handle,
     core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    ecs::system::SystemParam,
     prelude::*,
     render::{
-        camera::ExtractedCamera,
         render_resource::{
-            binding_types::{sampler, texture_2d, uniform_buffer},
-            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
-            ColorTargetState, ColorWrites, FragmentState, MultisampleState, Operations,
+            binding_types::{sampler, texture_2d, texture_storage_2d, uniform_buffer},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedComputePipelineId,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, ComputePassDescriptor,
+            ComputePipelineDescriptor, DynamicUniformBuffer, FragmentState, MultisampleState,
             PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
             RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderStages,
+            ShaderType, SpecializedComputePipeline, SpecializedComputePipelines,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, StorageTextureAccess,
             TextureFormat, TextureSampleType, UniformBuffer,
         },
         renderer::{RenderContext, RenderDevice, RenderQueue},
-        texture::CachedTexture,
     },
 };
 
+use crate::{
+    distance::{DistancePipeline, DistancePipelineKey},
+    plugin::{VoronoiCamera, VoronoiTexture},
+};
+
 pub const FLOOD_SEED_SHADER: Handle<Shader> = weak_handle!("75d5b3e1-ce23-4fcf-8a24-91e9bd6eeed8");
 pub const FLOOD_SHADER: Handle<Shader> = weak_handle!("7ac1a62e-dd67-404f-a5f6-3633b8bdcbef");
+pub const FLOOD_FUNCTIONS_SHADER: Handle<Shader> =
+    weak_handle!("0c5e2d7a-8f43-4b1e-a6d9-2b7f8e3c91d4");
+pub const FLOOD_COMPUTE_SHADER: Handle<Shader> =
+    weak_handle!("e4a1c9b2-5d37-4f80-9c2e-7a6b3d18f05c");
+
+const FLOOD_WORKGROUP_SIZE: u32 = 8;
+
+/// Settings of a single flood pass.
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct FloodUniform {
+    pub step: UVec2,
+    /// Seeds further than this many texels are ignored.
+    pub max_distance: f32,
+    /// Exponent of [`DistanceMetric::Minkowski`].
+    pub minkowski_p: f32,
+}
+
+/// Distance used to compare seeds in the flood passes.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum DistanceMetric {
+    #[default]
+    Euclidean,
+    /// Sum of the absolute axis distances, giving diamond shaped cells.
+    Manhattan,
+    /// Largest absolute axis distance, giving square cells.
+    Chebyshev,
+    /// Minkowski distance of order `p`. `p = 1` and `p = 2` match [`Self::Manhattan`]
+    /// and [`Self::Euclidean`], larger values approach [`Self::Chebyshev`]. `p` is
+    /// clamped to [`DistanceMetric::MIN_MINKOWSKI_P`].
+    Minkowski { p: f32 },
+}
+
+impl DistanceMetric {
+    /// Smallest Minkowski order, lower orders are no longer a metric the flood can
+    /// propagate and zero, negative and NaN orders give infinite or NaN distances.
+    pub const MIN_MINKOWSKI_P: f32 = 1.0;
+
+    pub fn distance(self, a: Vec2, b: Vec2) -> f32 {
+        let d = (a - b).abs();
+        match self {
+            DistanceMetric::Euclidean => d.length(),
+            DistanceMetric::Manhattan => d.x + d.y,
+            DistanceMetric::Chebyshev => d.max_element(),
+            DistanceMetric::Minkowski { .. } => {
+                let p = self.minkowski_p();
+                (d.x.powf(p) + d.y.powf(p)).powf(1.0 / p)
+            }
+        }
+    }
+
+    pub fn shader_def(self) -> &'static str {
+        match self {
+            DistanceMetric::Euclidean => "DISTANCE_EUCLIDEAN",
+            DistanceMetric::Manhattan => "DISTANCE_MANHATTAN",
+            DistanceMetric::Chebyshev => "DISTANCE_CHEBYSHEV",
+            DistanceMetric::Minkowski { .. } => "DISTANCE_MINKOWSKI",
+        }
+    }
+
+    /// Exponent passed to the shaders, clamped to [`DistanceMetric::MIN_MINKOWSKI_P`].
+    pub(crate) fn minkowski_p(self) -> f32 {
+        match self {
+            DistanceMetric::Minkowski { p } => p.max(Self::MIN_MINKOWSKI_P),
+            _ => 2.0,
+        }
+    }
+}
+
+/// Weight of a seed in the flood comparison. Seeds are compared by
+/// `(distance / multiplicative)² - additive`: `additive` (in squared texels) grows the
+/// cells of a power diagram and `multiplicative` those of a multiplicatively weighted
+/// diagram. Stored as `f32` bits in the `yz` channels of the object texture.
+/// `multiplicative` is clamped to [`VoronoiWeight::MIN_MULTIPLICATIVE`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VoronoiWeight {
+    pub multiplicative: f32,
+    pub additive: f32,
+}
+
+impl Default for VoronoiWeight {
+    fn default() -> Self {
+        Self {
+            multiplicative: 1.0,
+            additive: 0.0,
+        }
+    }
+}
+
+impl VoronoiWeight {
+    /// Smallest multiplicative weight, zero, negative and NaN weights would divide by
+    /// zero or flip the comparison.
+    pub const MIN_MULTIPLICATIVE: f32 = 1e-3;
+
+    pub fn multiplicative(weight: f32) -> Self {
+        Self {
+            multiplicative: weight,
+            ..default()
+        }
+    }
+
+    pub fn additive(weight: f32) -> Self {
+        Self {
+            additive: weight,
+            ..default()
+        }
+    }
+
+    /// The weight with `multiplicative` clamped, as written to the object texture.
+    pub fn clamped(self) -> Self {
+        Self {
+            multiplicative: self.multiplicative.max(Self::MIN_MULTIPLICATIVE),
+            ..self
+        }
+    }
+
+    pub fn weighted_distance(self, distance: f32) -> f32 {
+        let distance = distance / self.clamped().multiplicative;
+        distance * distance - self.additive
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FloodPipelineKey {
+    pub metric_shader_def: &'static str,
+    pub second_seed: bool,
+}
+
+/// Which pixels of the mask become seeds and where the seeds are placed, also deciding
+/// which pixels are inside the mask, see [`SeedMode::is_inside`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum SeedMode {
+    /// Fully opaque pixels next to a pixel with a different alpha, at their center.
+    #[default]
+    Edge,
+    /// Pixels on both sides of the `0.5` alpha crossing, all placed at the crossing
+    /// estimated by the inside pixel from its coverage and gradient like `Subpixel`, so the
+    /// distances are symmetric on both sides of the edge. Halfway towards the other side
+    /// where the gradient vanishes.
+    TwoSided,
+    /// Partially covered pixels and hard edges, placed at the estimated `0.5` alpha
+    /// crossing from the coverage and its gradient, like anti-aliased EDT. Produces
+    /// smooth fields from anti-aliased alpha masks.
+    Subpixel,
+}
+
+impl SeedMode {
+    /// Whether a pixel with this mask alpha is inside, giving negative signed distances.
+    /// Any coverage counts with [`SeedMode::Edge`], the modes placing seeds on the `0.5`
+    /// alpha crossing need an alpha of at least `0.5`.
+    pub fn is_inside(self, alpha: f32) -> bool {
+        match self {
+            SeedMode::Edge => alpha > 0.0,
+            SeedMode::TwoSided | SeedMode::Subpixel => alpha >= 0.5,
+        }
+    }
+
+    pub fn shader_def(self) -> Option<&'static str> {
+        match self {
+            SeedMode::Edge => None,
+            SeedMode::TwoSided => Some("SEED_TWO_SIDED"),
+            SeedMode::Subpixel => Some("SEED_SUBPIXEL"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FloodSeedPipelineKey {
+    pub seed_mode: SeedMode,
+    pub second_seed: bool,
+}
 
 #[derive(Resource)]
-pub struct FloodPipeline {
-    pub seed_layout: BindGroupLayout,
-    pub seed_pipeline: CachedRenderPipelineId,
+pub struct FloodSeedPipeline {
     pub layout: BindGroupLayout,
-    pub pipeline: CachedRenderPipelineId,
 }
 
-impl FromWorld for FloodPipeline {
+impl FromWorld for FloodSeedPipeline {
     fn from_world(world: &mut World) -> Self {
-        let seed_layout = world.resource::<RenderDevice>().create_bind_group_layout(
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
             "flood_seed_bind_group_layout",
             &BindGroupLayoutEntries::sequential(
                 ShaderStages::FRAGMENT,
                 (
                     texture_2d(TextureSampleType::Float { filterable: true }),
                     sampler(SamplerBindingType::Filtering),
+                    texture_2d(TextureSampleType::Uint),
                 ),
             ),
         );
 
-        let seed_pipeline =
-            world
-                .resource::<PipelineCache>()
-                .queue_render_pipeline(RenderPipelineDescriptor {
-                    label: Some("flood_seed_pipeline".into()),
-                    layout: vec![seed_layout.clone()],
-                    vertex: fullscreen_shader_vertex_state(),
-                    fragment: Some(FragmentState {
-                        shader: FLOOD_SEED_SHADER,
-                        shader_defs: vec![],
-                        entry_point: "fragment".into(),
-                        targets: vec![Some(ColorTargetState {
-                            format: TextureFormat::Rgba16Float,
-                            blend: None,
-                            write_mask: ColorWrites::ALL,
-                        })],
-                    }),
-                    push_constant_ranges: vec![],
-                    primitive: Default::default(),
-                    depth_stencil: None,
-                    multisample: MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    zero_initialize_workgroup_memory: false,
-                });
+        Self { layout }
+    }
+}
 
-        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+impl SpecializedRenderPipeline for FloodSeedPipeline {
+    type Key = FloodSeedPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let shader_defs = key
+            .seed_mode
+            .shader_def()
+            .into_iter()
+            .chain(key.second_seed.then_some("SECOND_SEED"))
+            .map(Into::into)
+            .collect();
+
+        RenderPipelineDescriptor {
+            label: Some("flood_seed_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: FLOOD_SEED_SHADER,
+                shader_defs,
+                entry_point: "fragment".into(),
+                targets: flood_targets(key.second_seed),
+            }),
+            push_constant_ranges: vec![],
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+}
+
+/// Seed and object targets of the seed and flood passes, followed by the second seed
+/// target when enabled.
+fn flood_targets(second_seed: bool) -> Vec<Option<ColorTargetState>> {
+    let mut formats = vec![TextureFormat::Rgba16Float, TextureFormat::Rgba32Uint];
+    if second_seed {
+        formats.push(TextureFormat::Rgba32Uint);
+    }
+
+    formats
+        .into_iter()
+        .map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })
+        })
+        .collect()
+}
+
+#[derive(Resource)]
+pub struct FloodPipeline {
+    pub layout: BindGroupLayout,
+    /// Layout with the second seed texture bound after the uniform.
+    pub second_layout: BindGroupLayout,
+}
+
+impl FromWorld for FloodPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
             "flood_bind_group_layout",
             &BindGroupLayoutEntries::sequential(
                 ShaderStages::FRAGMENT,
                 (
                     texture_2d(TextureSampleType::Float { filterable: true }),
-                    sampler(SamplerBindingType::Filtering),
-                    uniform_buffer::<UVec2>(false),
+                    texture_2d(TextureSampleType::Uint),
+                    uniform_buffer::<FloodUniform>(false),
                 ),
             ),
         );
 
-        let pipeline =
-            world
-                .resource::<PipelineCache>()
-                .queue_render_pipeline(RenderPipelineDescriptor {
-                    label: Some("flood_pipeline".into()),
-                    layout: vec![layout.clone()],
-                    vertex: fullscreen_shader_vertex_state(),
-                    fragment: Some(FragmentState {
-                        shader: FLOOD_SHADER,
-                        shader_defs: vec![],
-                        entry_point: "fragment".into(),
-                        targets: vec![Some(ColorTargetState {
-                            format: TextureFormat::Rgba16Float,
-                            blend: None,
-                            write_mask: ColorWrites::ALL,
-                        })],
-                    }),
-                    push_constant_ranges: vec![],
-                    primitive: Default::default(),
-                    depth_stencil: None,
-                    multisample: MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    zero_initialize_workgroup_memory: false,
-                });
+        let second_layout = render_device.create_bind_group_layout(
+            "flood_second_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Uint),
+                    uniform_buffer::<FloodUniform>(false),
+                    texture_2d(TextureSampleType::Uint),
+                ),
+            ),
+        );
 
         Self {
-            seed_pipeline,
-            seed_layout,
             layout,
-            pipeline,
+            second_layout,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for FloodPipeline {
+    type Key = FloodPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("flood_pipeline".into()),
+            layout: vec![if key.second_seed {
+                self.second_layout.clone()
+            } else {
+                self.layout.clone()
+            }],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: FLOOD_SHADER,
+                shader_defs: iter::once(key.metric_shader_def)
+                    .chain(key.second_seed.then_some("SECOND_SEED"))
+                    .map(Into::into)
+                    .collect(),
+                entry_point: "fragment".into(),
+                targets: flood_targets(key.second_seed),
+            }),
+            push_constant_ranges: vec![],
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            zero_initialize_workgroup_memory: false,
         }
     }
 }
 
+/// Order of the jump flood steps.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum JfaSchedule {
+    /// 1+JFA: an initial pass of step one followed by the halving steps.
+    OnePlusJfa,
+    /// JFA+1: the halving steps followed by an additional pass of step one.
+    #[default]
+    JfaPlusOne,
+    /// JFA+2: the halving steps followed by additional passes of step two and one.
+    JfaPlusTwo,
+    /// JFA²: the halving steps run twice.
+    JfaSquared,
+    /// Halving steps starting at `max_step` rounded up to a power of two, followed by an
+    /// additional pass of step one. Seeds only propagate up to about twice `max_step`.
+    Capped { max_step: u32 },
+}
+
+impl JfaSchedule {
+    /// Schedule of a flood limited to `max_distance` texels, which overrides `self` with
+    /// [`JfaSchedule::Capped`] at that distance since longer steps would only find seeds
+    /// out of range.
+    pub fn with_max_distance(self, max_distance: Option<f32>) -> Self {
+        match max_distance {
+            Some(max_distance) => JfaSchedule::Capped {
+                max_step: max_distance.ceil() as u32,
+            },
+            None => self,
+        }
+    }
+
+    /// Step sizes of every jump flood pass for a texture of the given size.
+    pub fn steps(self, size: UVec2) -> Vec<UVec2> {
+        let mut steps = Vec::new();
+
+        match self {
+            JfaSchedule::OnePlusJfa => {
+                steps.push(UVec2::ONE);
+                steps.extend(halving_steps(size));
+            }
+            JfaSchedule::JfaPlusOne => {
+                steps.extend(halving_steps(size));
+                steps.push(UVec2::ONE);
+            }
+            JfaSchedule::JfaPlusTwo => {
+                steps.extend(halving_steps(size));
+                steps.push(UVec2::splat(2));
+                steps.push(UVec2::ONE);
+            }
+            JfaSchedule::JfaSquared => {
+                steps.extend(halving_steps(size));
+                steps.extend(halving_steps(size));
+            }
+            JfaSchedule::Capped { max_step } => {
+                let max_dim = size.x.max(size.y);
+                let mut step = max_step.max(1).next_power_of_two().min(max_dim / 2);
+
+                while step >= 1 {
+                    steps.push(UVec2::splat(step));
+                    step /= 2;
+                }
+                steps.push(UVec2::ONE);
+            }
+        }
+
+        steps
+    }
+}
+
+/// Halving steps from half the largest dimension down to one, scaled per axis by the
+/// texture aspect ratio.
+fn halving_steps(size: UVec2) -> Vec<UVec2> {
+    let max_dim = size.x.max(size.y);
+    let mut step = max_dim / 2;
+    let mut steps = Vec::new();
+
+    while step >= 1 {
+        let x_step = (step * size.x) / max_dim;
+        let y_step = (step * size.y) / max_dim;
+
+        steps.push(UVec2::new(x_step.max(1), y_step.max(1)));
+        step /= 2;
+    }
+
+    steps
+}
+
+/// Compute variant of the flood passes, writing to storage textures. Only available
+/// when the render device supports storage textures.
+#[derive(Resource)]
+pub struct FloodComputePipeline {
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for FloodComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "flood_compute_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Uint),
+                    texture_storage_2d(TextureFormat::Rgba16Float, StorageTextureAccess::WriteOnly),
+                    texture_storage_2d(TextureFormat::Rgba32Uint, StorageTextureAccess::WriteOnly),
+                    uniform_buffer::<FloodUniform>(true),
+                ),
+            ),
+        );
+
+        Self { layout }
+    }
+}
+
+impl SpecializedComputePipeline for FloodComputePipeline {
+    type Key = FloodPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> ComputePipelineDescriptor {
+        ComputePipelineDescriptor {
+            label: Some("flood_compute_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: vec![],
+            shader: FLOOD_COMPUTE_SHADER,
+            shader_defs: vec![key.metric_shader_def.into()],
+            entry_point: "flood".into(),
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+}
+
+/// Flood pipelines specialized for the settings of a view.
+#[derive(Component)]
+pub struct ViewFloodPipelines {
+    pub seed_pipeline: CachedRenderPipelineId,
+    pub pipeline: CachedRenderPipelineId,
+    pub compute_pipeline: Option<CachedComputePipelineId>,
+    pub distance_pipeline: Option<CachedRenderPipelineId>,
+}
+
+/// The flood pipelines of every pass, with the caches of their specializations.
+#[derive(SystemParam)]
+pub struct FloodPipelineSpecializers<'w> {
+    flood_seed_pipeline: Res<'w, FloodSeedPipeline>,
+    seed_pipelines: ResMut<'w, SpecializedRenderPipelines<FloodSeedPipeline>>,
+    flood_pipeline: Res<'w, FloodPipeline>,
+    pipelines: ResMut<'w, SpecializedRenderPipelines<FloodPipeline>>,
+    flood_compute_pipeline: Option<Res<'w, FloodComputePipeline>>,
+    compute_pipelines: ResMut<'w, SpecializedComputePipelines<FloodComputePipeline>>,
+    distance_pipeline: Res<'w, DistancePipeline>,
+    distance_pipelines: ResMut<'w, SpecializedRenderPipelines<DistancePipeline>>,
+}
+
+pub fn prepare_flood_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    specializers: FloodPipelineSpecializers,
+    views: Query<(Entity, &VoronoiCamera)>,
+) {
+    let FloodPipelineSpecializers {
+        flood_seed_pipeline,
+        mut seed_pipelines,
+        flood_pipeline,
+        mut pipelines,
+        flood_compute_pipeline,
+        mut compute_pipelines,
+        distance_pipeline,
+        mut distance_pipelines,
+    } = specializers;
+
+    for (entity, voronoi_camera) in &views {
+        let key = FloodPipelineKey {
+            metric_shader_def: voronoi_camera.metric.shader_def(),
+            second_seed: voronoi_camera.second_seed,
+        };
+
+        let seed_key = FloodSeedPipelineKey {
+            seed_mode: voronoi_camera.seed_mode,
+            second_seed: voronoi_camera.second_seed,
+        };
+
+        let distance_key = DistancePipelineKey {
+            metric_shader_def: key.metric_shader_def,
+            seed_mode: voronoi_camera.seed_mode,
+        };
+
+        commands.entity(entity).insert(ViewFloodPipelines {
+            seed_pipeline: seed_pipelines.specialize(
+                &pipeline_cache,
+                &flood_seed_pipeline,
+                seed_key,
+            ),
+            pipeline: pipelines.specialize(&pipeline_cache, &flood_pipeline, key),
+            compute_pipeline: flood_compute_pipeline
+                .as_ref()
+                .filter(|_| !voronoi_camera.second_seed)
+                .map(|flood_compute_pipeline| {
+                    compute_pipelines.specialize(&pipeline_cache, flood_compute_pipeline, key)
+                }),
+            distance_pipeline: voronoi_camera.distance_field.then(|| {
+                distance_pipelines.specialize(&pipeline_cache, &distance_pipeline, distance_key)
+            }),
+        });
+    }
+}
+
+/// Seed, object and, when enabled, second seed outputs of the seed and flood passes.
+fn flood_color_attachments(
+    voronoi_textures: &VoronoiTexture,
+) -> Vec<Option<RenderPassColorAttachment<'_>>> {
+    iter::once(voronoi_textures.output())
+        .chain(iter::once(voronoi_textures.object_output()))
+        .chain(voronoi_textures.second_output())
+        .map(|texture| {
+            Some(RenderPassColorAttachment {
+                view: &texture.default_view,
+                resolve_target: None,
+                ops: voronoi_textures.output_operations(),
+            })
+        })
+        .collect()
+}
+
 pub fn run_flood_seed_pass<'w>(
     world: &'w World,
     render_context: &mut RenderContext<'w>,
-    camera: &ExtractedCamera,
-    input: &CachedTexture,
-    output: &CachedTexture,
+    voronoi_textures: &VoronoiTexture,
+    pipeline: CachedRenderPipelineId,
 ) {
-    let flood_pipeline = world.resource::<FloodPipeline>();
+    let flood_seed_pipeline = world.resource::<FloodSeedPipeline>();
 
     let Some(pipeline) = world
         .resource::<PipelineCache>()
-        .get_render_pipeline(flood_pipeline.seed_pipeline)
+        .get_render_pipeline(pipeline)
     else {
         return;
     };
@@ -140,24 +596,21 @@ pub fn run_flood_seed_pass<'w>(
 
     let bind_group = render_context.render_device().create_bind_group(
         "flood_seed_bind_group",
-        &flood_pipeline.seed_layout,
-        &BindGroupEntries::sequential((&input.default_view, &sampler)),
+        &flood_seed_pipeline.layout,
+        &BindGroupEntries::sequential((
+            &voronoi_textures.input().default_view,
+            &sampler,
+            &voronoi_textures.object_input().default_view,
+        )),
     );
 
     let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
         label: Some("flood_seed_pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-            view: &output.default_view,
-            resolve_target: None,
-            ops: Operations::default(),
-        })],
+        color_attachments: &flood_color_attachments(voronoi_textures),
         ..default()
     });
 
-    if let Some(viewport) = camera.viewport.as_ref() {
-        pass.set_camera_viewport(viewport);
-    }
-
+    voronoi_textures.set_scissor(&mut pass);
     pass.set_render_pipeline(pipeline);
     pass.set_bind_group(0, &bind_group, &[]);
     pass.draw(0..3, 0..1);
@@ -166,54 +619,159 @@ pub fn run_flood_seed_pass<'w>(
 pub fn run_flood_pass<'w>(
     world: &'w World,
     render_context: &mut RenderContext<'w>,
-    camera: &ExtractedCamera,
-    input: &CachedTexture,
-    output: &CachedTexture,
-    step: UVec2,
+    voronoi_textures: &VoronoiTexture,
+    pipeline: CachedRenderPipelineId,
+    settings: FloodUniform,
 ) {
     let flood_pipeline = world.resource::<FloodPipeline>();
 
-    let mut step = UniformBuffer::from(step);
+    let mut settings = UniformBuffer::from(settings);
 
-    step.write_buffer(
+    settings.write_buffer(
         render_context.render_device(),
         world.resource::<RenderQueue>(),
     );
 
-    let (Some(pipeline), Some(step)) = (
+    let (Some(pipeline), Some(settings)) = (
         world
             .resource::<PipelineCache>()
-            .get_render_pipeline(flood_pipeline.pipeline),
-        step.binding(),
+            .get_render_pipeline(pipeline),
+        settings.binding(),
     ) else {
         return;
     };
 
-    let sampler = render_context
-        .render_device()
-        .create_sampler(&SamplerDescriptor::default());
-
-    let bind_group = render_context.render_device().create_bind_group(
-        "flood_bind_group",
-        &flood_pipeline.layout,
-        &BindGroupEntries::sequential((&input.default_view, &sampler, step)),
-    );
+    let bind_group = match voronoi_textures.second_input() {
+        Some(second_input) => render_context.render_device().create_bind_group(
+            "flood_second_bind_group",
+            &flood_pipeline.second_layout,
+            &BindGroupEntries::sequential((
+                &voronoi_textures.input().default_view,
+                &voronoi_textures.object_input().default_view,
+                settings,
+                &second_input.default_view,
+            )),
+        ),
+        None => render_context.render_device().create_bind_group(
+            "flood_bind_group",
+            &flood_pipeline.layout,
+            &BindGroupEntries::sequential((
+                &voronoi_textures.input().default_view,
+                &voronoi_textures.object_input().default_view,
+                settings,
+            )),
+        ),
+    };
 
     let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
         label: Some("flood_pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-            view: &output.default_view,
-            resolve_target: None,
-            ops: Operations::default(),
-        })],
+        color_attachments: &flood_color_attachments(voronoi_textures),
         ..default()
     });
 
-    if let Some(viewport) = camera.viewport.as_ref() {
-        pass.set_camera_viewport(viewport);
-    }
-
+    voronoi_textures.set_scissor(&mut pass);
     pass.set_render_pipeline(pipeline);
     pass.set_bind_group(0, &bind_group, &[]);
     pass.draw(0..3, 0..1);
 }
+
+/// Runs every flood pass in a single compute pass, selecting the settings of each pass
+/// through a dynamic offset into one uniform buffer. Flips `voronoi_textures` once per
+/// pass.
+pub fn run_flood_compute_passes<'w>(
+    world: &'w World,
+    render_context: &mut RenderContext<'w>,
+    voronoi_textures: &mut VoronoiTexture,
+    pipeline: CachedComputePipelineId,
+    passes: &[FloodUniform],
+) {
+    let flood_pipeline = world.resource::<FloodComputePipeline>();
+
+    let mut settings_buffer = DynamicUniformBuffer::<FloodUniform>::default();
+    let offsets: Vec<u32> = passes
+        .iter()
+        .map(|settings| settings_buffer.push(settings))
+        .collect();
+
+    settings_buffer.write_buffer(
+        render_context.render_device(),
+        world.resource::<RenderQueue>(),
+    );
+
+    let (Some(pipeline), Some(settings_binding)) = (
+        world
+            .resource::<PipelineCache>()
+            .get_compute_pipeline(pipeline),
+        settings_buffer.binding(),
+    ) else {
+        for _ in passes {
+            voronoi_textures.flip();
+        }
+        return;
+    };
+
+    let mut bind_groups = Vec::with_capacity(2);
+    for _ in 0..2 {
+        bind_groups.push(render_context.render_device().create_bind_group(
+            "flood_compute_bind_group",
+            &flood_pipeline.layout,
+            &BindGroupEntries::sequential((
+                &voronoi_textures.input().default_view,
+                &voronoi_textures.object_input().default_view,
+                &voronoi_textures.output().default_view,
+                &voronoi_textures.object_output().default_view,
+                settings_binding.clone(),
+            )),
+        ));
+        voronoi_textures.flip();
+    }
+
+    let size = voronoi_textures.input().texture.size();
+    let workgroups_x = size.width.div_ceil(FLOOD_WORKGROUP_SIZE);
+    let workgroups_y = size.height.div_ceil(FLOOD_WORKGROUP_SIZE);
+
+    let mut pass = render_context
+        .command_encoder()
+        .begin_compute_pass(&ComputePassDescriptor {
+            label: Some("flood_compute_pass"),
+            timestamp_writes: None,
+        });
+
+    pass.set_pipeline(pipeline);
+
+    for (i, offset) in offsets.into_iter().enumerate() {
+        pass.set_bind_group(0, &bind_groups[i % 2], &[offset]);
+        pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+        voronoi_textures.flip();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Vec2 = Vec2::new(1.0, 2.0);
+    const B: Vec2 = Vec2::new(4.0, -2.0);
+
+    #[test]
+    fn metrics_match_known_distances() {
+        assert_eq!(DistanceMetric::Euclidean.distance(A, B), 5.0);
+        assert_eq!(DistanceMetric::Manhattan.distance(A, B), 7.0);
+        assert_eq!(DistanceMetric::Chebyshev.distance(A, B), 4.0);
+
+        let minkowski = |p| DistanceMetric::Minkowski { p }.distance(A, B);
+        assert!((minkowski(1.0) - 7.0).abs() < 1e-5);
+        assert!((minkowski(2.0) - 5.0).abs() < 1e-5);
+        assert!((minkowski(3.0) - 91f32.cbrt()).abs() < 1e-5);
+        assert!((minkowski(16.0) - 4.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn minkowski_order_is_clamped() {
+        for p in [0.0, -2.0, 0.5, f32::NAN] {
+            let metric = DistanceMetric::Minkowski { p };
+            assert_eq!(metric.minkowski_p(), DistanceMetric::MIN_MINKOWSKI_P);
+            assert!((metric.distance(A, B) - 7.0).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#import bevy_voronoi::flood::{flood_texel, FloodUniform}
+
+@group(0) @binding(0) var seed_texture: texture_2d<f32>;
+@group(0) @binding(1) var object_texture: texture_2d<u32>;
+@group(0) @binding(2) var seed_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(3) var object_output: texture_storage_2d<rgba32uint, write>;
+@group(0) @binding(4) var<uniform> settings: FloodUniform;
+
+@compute @workgroup_size(8, 8, 1)
+fn flood(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    if any(invocation_id.xy >= textureDimensions(seed_texture)) {
+        return;
+    }
+
+    let texel = vec2<i32>(invocation_id.xy);
+    let flooded = flood_texel(seed_texture, object_texture, texel, settings);
+
+    textureStore(seed_output, texel, flooded.seed);
+    textureStore(object_output, texel, flooded.object);
+}
@@ -0,0 +1,213 @@
+#define_import_path bevy_voronoi::flood
+
+struct FloodUniform {
+    step: vec2<u32>,
+    max_distance: f32,
+    minkowski_p: f32,
+}
+
+// Largest finite f32, the distance of texels without a seed so that every seed in range is
+// nearer, like `f32::MAX` on the CPU
+const FLOAT_MAX: f32 = 3.40282347e38;
+
+struct FloodTexel {
+    seed: vec4<f32>,
+    object: vec4<u32>,
+}
+
+// Distance between two points in the metric selected by the shader defs
+fn metric_distance(a: vec2<f32>, b: vec2<f32>, minkowski_p: f32) -> f32 {
+    let d = abs(a - b);
+#ifdef DISTANCE_MANHATTAN
+    return d.x + d.y;
+#else ifdef DISTANCE_CHEBYSHEV
+    return max(d.x, d.y);
+#else ifdef DISTANCE_MINKOWSKI
+    return pow(pow(d.x, minkowski_p) + pow(d.y, minkowski_p), 1.0 / minkowski_p);
+#else
+    return length(d);
+#endif
+}
+
+// Weighted distance of a seed, with the weights of its object stored as f32 bits in
+// `yz`, see `VoronoiWeight`
+fn weighted_distance(distance: f32, object: vec4<u32>) -> f32 {
+    let d = distance / bitcast<f32>(object.y);
+    return d * d - bitcast<f32>(object.z);
+}
+
+// Picks the nearest seed by weighted distance among the 3x3 neighbours `step` texels apart, ignoring seeds
+// further than `max_distance`. Texels left without a seed are marked as far (z = -1).
+fn flood_texel(
+    seed_texture: texture_2d<f32>,
+    object_texture: texture_2d<u32>,
+    texel: vec2<i32>,
+    settings: FloodUniform,
+) -> FloodTexel {
+    let original_seed = textureLoad(seed_texture, texel, 0);
+    let original_object = textureLoad(object_texture, texel, 0);
+
+    var out: FloodTexel;
+    out.seed = original_seed;
+    out.object = original_object;
+
+    if original_seed.z == 1. {
+        return out;
+    }
+
+    let position = vec2<f32>(texel) + 0.5;
+    let max_texel = vec2<i32>(textureDimensions(seed_texture)) - 1;
+
+    var current_seed = original_seed.xy;
+    var current_object = original_object;
+    var current_dist = FLOAT_MAX;
+    var current_weighted_dist = FLOAT_MAX;
+
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            // Clamp to edge, like the sampler addressing used by the other passes
+            let neighbour_texel = clamp(texel + vec2<i32>(x, y) * vec2<i32>(settings.step), vec2(0), max_texel);
+            let neighbour_seed = textureLoad(seed_texture, neighbour_texel, 0).xy;
+            let neighbour_object = textureLoad(object_texture, neighbour_texel, 0);
+            let neighbour_dist = metric_distance(position, neighbour_seed, settings.minkowski_p);
+            let neighbour_weighted_dist = weighted_distance(neighbour_dist, neighbour_object);
+
+            if neighbour_seed.x >= 0. && neighbour_seed.y >= 0.
+                && neighbour_dist <= settings.max_distance
+                && neighbour_weighted_dist < current_weighted_dist {
+                current_seed = neighbour_seed;
+                current_object = neighbour_object;
+                current_dist = neighbour_dist;
+                current_weighted_dist = neighbour_weighted_dist;
+            }
+        }
+    }
+
+    let is_far = current_dist > settings.max_distance;
+    out.seed = vec4<f32>(select(current_seed, vec2(-1.0), is_far), select(0.0, -1.0, is_far), original_seed.w);
+    // Far texels keep their own object, like `VoronoiField::flood`
+    out.object = select(current_object, original_object, is_far);
+    return out;
+}
+
+// Second nearest seed of a texel, the seed position as f16 in `x` and the object of the
+// seed in `yzw`
+fn pack_second_seed(seed: vec2<f32>, object: vec4<u32>) -> vec4<u32> {
+    return vec4(pack2x16float(seed), object.xyz);
+}
+
+fn unpack_second_seed(second: vec4<u32>) -> vec2<f32> {
+    return unpack2x16float(second.x);
+}
+
+fn unpack_second_object(second: vec4<u32>) -> vec4<u32> {
+    return vec4(second.yzw, 0u);
+}
+
+// Signed distance from `position` to the border between the cells of `seed` and
+// `second_seed`, positive inside the cell of `seed`. Exact for the Euclidean metric
+// without weights. `FLOAT_MAX` without a second seed (-1, -1), `0` when both seeds share
+// a position.
+fn cell_edge_distance(position: vec2<f32>, seed: vec2<f32>, second_seed: vec2<f32>) -> f32 {
+    if second_seed.x < 0. || second_seed.y < 0. {
+        return FLOAT_MAX;
+    }
+    let direction = second_seed - seed;
+    if all(direction == vec2(0.)) {
+        return 0.;
+    }
+    return dot(0.5 * (seed + second_seed) - position, normalize(direction));
+}
+
+struct FloodTexelSecond {
+    seed: vec4<f32>,
+    object: vec4<u32>,
+    second: vec4<u32>,
+}
+
+// Like `flood_texel`, also keeping the nearest seed of another object than the nearest
+// seed, packed by `pack_second_seed`. The second seeds of the neighbours are candidates
+// too. Original seeds stay their own nearest seed.
+fn flood_texel_second(
+    seed_texture: texture_2d<f32>,
+    object_texture: texture_2d<u32>,
+    second_texture: texture_2d<u32>,
+    texel: vec2<i32>,
+    settings: FloodUniform,
+) -> FloodTexelSecond {
+    let original_seed = textureLoad(seed_texture, texel, 0);
+    let original_object = textureLoad(object_texture, texel, 0);
+    let is_seed = original_seed.z == 1.;
+
+    let position = vec2<f32>(texel) + 0.5;
+    let max_texel = vec2<i32>(textureDimensions(seed_texture)) - 1;
+
+    var nearest_seed = vec2(-1.0);
+    var nearest_object = vec4(0u);
+    var nearest_dist = FLOAT_MAX;
+    var nearest_weighted_dist = FLOAT_MAX;
+    if is_seed {
+        nearest_seed = original_seed.xy;
+        nearest_object = original_object;
+        nearest_dist = 0.;
+        nearest_weighted_dist = -FLOAT_MAX;
+    }
+
+    var second_seed = vec2(-1.0);
+    var second_object = vec4(0u);
+    var second_weighted_dist = FLOAT_MAX;
+
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let neighbour_texel = clamp(texel + vec2<i32>(x, y) * vec2<i32>(settings.step), vec2(0), max_texel);
+            let neighbour_second = textureLoad(second_texture, neighbour_texel, 0);
+
+            for (var i = 0; i < 2; i++) {
+                var candidate_seed = textureLoad(seed_texture, neighbour_texel, 0).xy;
+                var candidate_object = textureLoad(object_texture, neighbour_texel, 0);
+                if i == 1 {
+                    candidate_seed = unpack_second_seed(neighbour_second);
+                    candidate_object = unpack_second_object(neighbour_second);
+                }
+
+                let candidate_dist = metric_distance(position, candidate_seed, settings.minkowski_p);
+                if candidate_seed.x < 0. || candidate_seed.y < 0.
+                    || candidate_dist > settings.max_distance {
+                    continue;
+                }
+
+                let candidate_weighted_dist = weighted_distance(candidate_dist, candidate_object);
+                let other_object = candidate_object.x != nearest_object.x;
+
+                if candidate_weighted_dist < nearest_weighted_dist {
+                    if other_object {
+                        second_seed = nearest_seed;
+                        second_object = nearest_object;
+                        second_weighted_dist = nearest_weighted_dist;
+                    }
+                    nearest_seed = candidate_seed;
+                    nearest_object = candidate_object;
+                    nearest_dist = candidate_dist;
+                    nearest_weighted_dist = candidate_weighted_dist;
+                } else if other_object && candidate_weighted_dist < second_weighted_dist {
+                    second_seed = candidate_seed;
+                    second_object = candidate_object;
+                    second_weighted_dist = candidate_weighted_dist;
+                }
+            }
+        }
+    }
+
+    var out: FloodTexelSecond;
+    if is_seed {
+        out.seed = original_seed;
+        out.object = original_object;
+    } else {
+        let is_far = nearest_dist > settings.max_distance;
+        out.seed = vec4<f32>(select(nearest_seed, vec2(-1.0), is_far), select(0.0, -1.0, is_far), original_seed.w);
+        // Far texels keep their own object, like `flood_texel`
+        out.object = select(nearest_object, original_object, is_far);
+    }
+    out.second = pack_second_seed(second_seed, second_object);
+    return out;
+}
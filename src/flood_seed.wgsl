@@ -1,32 +1,168 @@
 #import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#ifdef SECOND_SEED
+#import bevy_voronoi::flood::pack_second_seed
+#endif
 
 @group(0) @binding(0) var mask_texture: texture_2d<f32>;
 @group(0) @binding(1) var sampler_obj: sampler;
+@group(0) @binding(2) var object_texture: texture_2d<u32>;
+
+struct FragmentOutput {
+    @location(0) seed: vec4<f32>,
+    @location(1) object: vec4<u32>,
+#ifdef SECOND_SEED
+    @location(2) second: vec4<u32>,
+#endif
+}
+
+fn load_mask(texel: vec2<i32>) -> f32 {
+    let max_texel = vec2<i32>(textureDimensions(mask_texture)) - 1;
+    return textureLoad(mask_texture, clamp(texel, vec2(0), max_texel), 0).a;
+}
+
+// Distance from the pixel center to the 0.5 alpha crossing along the normalized alpha
+// gradient, assuming a straight edge through the pixel (Gustavson & Strand, anti-aliased
+// Euclidean distance transform). Positive when the center is outside.
+fn edge_distance(gradient: vec2<f32>, alpha: f32) -> f32 {
+    if gradient.x == 0.0 || gradient.y == 0.0 {
+        return 0.5 - alpha;
+    }
+
+    let g = abs(gradient);
+    let gx = max(g.x, g.y);
+    let gy = min(g.x, g.y);
+    let a1 = 0.5 * gy / gx;
+
+    if alpha < a1 {
+        return 0.5 * (gx + gy) - sqrt(2.0 * gx * gy * alpha);
+    } else if alpha < 1.0 - a1 {
+        return (0.5 - alpha) * gx;
+    }
+    return -0.5 * (gx + gy) + sqrt(2.0 * gx * gy * (1.0 - alpha));
+}
+
+// Sobel gradient of the mask, pointing inside
+fn mask_gradient(texel: vec2<i32>) -> vec2<f32> {
+    let s = sqrt(2.0);
+    let nw = load_mask(texel + vec2(-1, -1));
+    let n = load_mask(texel + vec2(0, -1));
+    let ne = load_mask(texel + vec2(1, -1));
+    let w = load_mask(texel + vec2(-1, 0));
+    let e = load_mask(texel + vec2(1, 0));
+    let sw = load_mask(texel + vec2(-1, 1));
+    let so = load_mask(texel + vec2(0, 1));
+    let se = load_mask(texel + vec2(1, 1));
+    return vec2(
+        ne + s * e + se - nw - s * w - sw,
+        sw + s * so + se - nw - s * n - ne,
+    );
+}
+
+// Center of `texel` moved along the alpha gradient to the estimated 0.5 crossing
+fn subpixel_crossing(texel: vec2<i32>) -> vec2<f32> {
+    let position = vec2<f32>(texel) + 0.5;
+    let gradient = mask_gradient(texel);
+    if all(gradient == vec2(0.0)) {
+        return position;
+    }
+
+    let normal = normalize(gradient);
+    return position + normal * edge_distance(normal, load_mask(texel));
+}
 
 @fragment
-fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+fn fragment(in: FullscreenVertexOutput) -> FragmentOutput {
     let screen_size = vec2<f32>(textureDimensions(mask_texture));
     let mask = textureSample(mask_texture, sampler_obj, in.uv).a;
     let null_seed = vec2(-1.0);
 
-    if mask != 1.0 {
-        return vec4(null_seed, 0.0, mask);
-    }
+    var out: FragmentOutput;
+    out.seed = vec4(null_seed, 0.0, mask);
+    out.object = vec4(0u);
+#ifdef SECOND_SEED
+    // Filled in by the flood passes
+    out.second = pack_second_seed(null_seed, vec4(0u));
+#endif
 
     let offsets = array<vec2<f32>, 4>(
         vec2<f32>(1.0, 0.0), vec2<f32>(-1.0, 0.0),
         vec2<f32>(0.0, 1.0), vec2<f32>(0.0, -1.0)
     );
 
+#ifdef SEED_TWO_SIDED
+    // Pixels on both sides of the 0.5 alpha crossing are seeds, all placed at the crossing
+    // estimated by the inside pixel so the distances are symmetric. Outside seeds take the
+    // object of the inside pixel across the edge.
+    let texel = vec2<i32>(in.position.xy);
+    let max_texel = vec2<i32>(screen_size) - 1;
+    let inside = mask >= 0.5;
+
+    var is_edge = false;
+    var direction = vec2(0.0);
+    var inside_texel = texel;
+
+    for (var i = 0; i < 4; i++) {
+        let neighbor_texel = clamp(texel + vec2<i32>(offsets[i]), vec2(0), max_texel);
+        let neighbor_inside = load_mask(neighbor_texel) >= 0.5;
+
+        if neighbor_inside != inside {
+            is_edge = true;
+            direction += offsets[i];
+            if !inside {
+                inside_texel = neighbor_texel;
+            }
+        }
+    }
+
+    if is_edge {
+        // Halfway towards the other side when the gradient vanishes
+        var position = in.position.xy;
+        if any(mask_gradient(inside_texel) != vec2(0.0)) {
+            position = subpixel_crossing(inside_texel);
+        } else if any(direction != vec2(0.0)) {
+            position += 0.5 * normalize(direction);
+        }
+        out.seed = vec4(position, 1.0, mask);
+        out.object = textureLoad(object_texture, inside_texel, 0);
+    }
+
+    return out;
+#else ifdef SEED_SUBPIXEL
+    // Partially covered pixels and opaque pixels next to empty ones are seeds, moved
+    // along the alpha gradient to the estimated 0.5 crossing
+    let texel = vec2<i32>(in.position.xy);
+
+    var is_edge = mask > 0.0 && mask < 1.0;
+    for (var i = 0; i < 4; i++) {
+        if mask == 1.0 && load_mask(texel + vec2<i32>(offsets[i])) == 0.0 {
+            is_edge = true;
+        }
+    }
+
+    if !is_edge {
+        return out;
+    }
+
+    out.seed = vec4(subpixel_crossing(texel), 1.0, mask);
+    out.object = textureLoad(object_texture, texel, 0);
+    return out;
+#else
+    if mask != 1.0 {
+        return out;
+    }
+
     for (var i = 0; i < 4; i++) {
         let neighbor_uv = (in.position.xy + offsets[i]) / screen_size;
-        let neighbor_mask = textureSample(mask_texture, sampler_obj, neighbor_uv).r;
+        let neighbor_mask = textureSample(mask_texture, sampler_obj, neighbor_uv).a;
 
         // Mark edge pixels as seeds, blue channel means original seed;
         if neighbor_mask != mask {
-            return vec4(in.position.xy, 1.0, mask);
+            out.seed = vec4(in.position.xy, 1.0, mask);
+            out.object = textureLoad(object_texture, vec2<i32>(in.position.xy), 0);
+            return out;
         }
     }
 
-    return vec4(null_seed, 0.0, mask);
+    return out;
+#endif
 }
@@ -1,7 +1,22 @@
+mod cpu;
+mod distance;
 mod flood;
 mod mask;
 mod plugin;
+mod readback;
+mod seed;
+mod sprite;
 
 pub mod prelude {
-    pub use crate::plugin::{Voronoi2dPlugin, VoronoiCamera, VoronoiMaterial, VoronoiTexture};
+    pub use crate::cpu::{AlphaMask, VoronoiField};
+    pub use crate::distance::VoronoiDistanceTexture;
+    pub use crate::flood::{DistanceMetric, JfaSchedule, SeedMode, VoronoiWeight};
+    pub use crate::mask::MaskChannel;
+    pub use crate::plugin::{
+        FloodMode, Voronoi2d, Voronoi2dPlugin, VoronoiCamera, VoronoiChannel, VoronoiChannelViews,
+        VoronoiChannels, VoronoiFloodProgress, VoronoiLayers, VoronoiMaterial, VoronoiOffscreen,
+        VoronoiTexture,
+    };
+    pub use crate::readback::{VoronoiReadback, VoronoiReadbackField};
+    pub use crate::seed::VoronoiSeed;
 }
@@ -2,11 +2,12 @@ use std::ops::Range;
 
 use bevy::{
     asset::{weak_handle, UntypedAssetId},
-    core_pipeline::core_2d::BatchSetKey2d,
+    core_pipeline::{
+        core_2d::BatchSetKey2d, fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
     ecs::system::{lifetimeless::SRes, SystemParamItem},
     prelude::*,
     render::{
-        camera::ExtractedCamera,
         mesh::MeshVertexBufferLayoutRef,
         render_asset::RenderAssets,
         render_phase::{
@@ -15,16 +16,17 @@ use bevy::{
             TrackedRenderPass, ViewBinnedRenderPhases,
         },
         render_resource::{
-            binding_types::{sampler, texture_2d},
+            binding_types::{sampler, texture_2d, uniform_buffer},
             BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries,
-            CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, Operations,
-            RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
-            SamplerBindingType, SamplerDescriptor, ShaderStages, SpecializedMeshPipeline,
-            SpecializedMeshPipelineError, TextureFormat, TextureSampleType,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, PipelineCache,
+            RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, Sampler,
+            SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType,
+            SpecializedMeshPipeline, SpecializedMeshPipelineError, TextureFormat,
+            TextureSampleType, TextureViewId, UniformBuffer,
         },
-        renderer::{RenderContext, RenderDevice},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
         sync_world::{MainEntity, MainEntityHashMap},
-        texture::{CachedTexture, FallbackImage, GpuImage},
+        texture::{FallbackImage, GpuImage},
         view::RetainedViewEntity,
     },
     sprite::{
@@ -32,19 +34,67 @@ use bevy::{
     },
 };
 
-use crate::plugin::RenderVoronoiMaterials;
+use crate::plugin::{RenderVoronoiMaterials, VoronoiTexture};
 
 pub const MASK_SHADER: Handle<Shader> = weak_handle!("cd8c10c3-d6ad-4676-9cd8-22f2df16b00d");
+pub const MASK_FUNCTIONS_SHADER: Handle<Shader> =
+    weak_handle!("a41f6d2e-9b37-4c85-b2d0-7e13c8f5a964");
+pub const MASK_CLEAR_SHADER: Handle<Shader> = weak_handle!("e7c3b1a8-4f62-4d09-9a5e-81d2f6c0b347");
+
+/// Channel of the alpha mask read as coverage by the mask pass.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum MaskChannel {
+    R,
+    G,
+    B,
+    #[default]
+    A,
+    /// Rec. 709 luminance of the linear color.
+    Luminance,
+}
+
+impl MaskChannel {
+    pub fn read(self, color: LinearRgba) -> f32 {
+        match self {
+            MaskChannel::R => color.red,
+            MaskChannel::G => color.green,
+            MaskChannel::B => color.blue,
+            MaskChannel::A => color.alpha,
+            MaskChannel::Luminance => {
+                0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+            }
+        }
+    }
+}
+
+pub const MASK_FLAG_INVERT: u32 = 1;
+pub const MASK_FLAG_THRESHOLD: u32 = 2;
+
+#[derive(ShaderType, Clone, Copy, Default, PartialEq)]
+pub struct MaskMaterialUniform {
+    pub id: u32,
+    pub multiplicative_weight: f32,
+    pub additive_weight: f32,
+    /// Index of the [`MaskChannel`].
+    pub channel: u32,
+    pub flags: u32,
+    pub threshold: f32,
+    pub uv_transform: Mat3,
+}
 
 #[derive(Resource)]
 pub struct MaskPipeline {
     pub mesh_pipeline: Mesh2dPipeline,
     pub material_layout: BindGroupLayout,
+    /// Sampler of the alpha masks, shared by every material.
+    pub sampler: Sampler,
 }
 
 impl FromWorld for MaskPipeline {
     fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
         Self {
+            sampler: render_device.create_sampler(&SamplerDescriptor::default()),
             mesh_pipeline: Mesh2dPipeline::from_world(world),
             material_layout: world.resource::<RenderDevice>().create_bind_group_layout(
                 "mask_material_bind_group_layout",
@@ -53,6 +103,7 @@ impl FromWorld for MaskPipeline {
                     (
                         texture_2d(TextureSampleType::Float { filterable: true }),
                         sampler(SamplerBindingType::Filtering),
+                        uniform_buffer::<MaskMaterialUniform>(false),
                     ),
                 ),
             ),
@@ -68,7 +119,7 @@ impl SpecializedMeshPipeline for MaskPipeline {
         key: Self::Key,
         layout: &MeshVertexBufferLayoutRef,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let descriptor = self.mesh_pipeline.specialize(key, &layout)?;
+        let descriptor = self.mesh_pipeline.specialize(key, layout)?;
 
         let mut mesh_layout = descriptor.layout.clone();
         mesh_layout.push(self.material_layout.clone());
@@ -80,11 +131,18 @@ impl SpecializedMeshPipeline for MaskPipeline {
                 shader: MASK_SHADER,
                 shader_defs: vec![],
                 entry_point: "fragment".into(),
-                targets: vec![Some(ColorTargetState {
-                    format: TextureFormat::Rgba16Float,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
+                targets: vec![
+                    Some(ColorTargetState {
+                        format: TextureFormat::Rgba16Float,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    }),
+                    Some(ColorTargetState {
+                        format: TextureFormat::Rgba32Uint,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    }),
+                ],
             }),
             depth_stencil: None,
             multisample: Default::default(),
@@ -93,6 +151,50 @@ impl SpecializedMeshPipeline for MaskPipeline {
     }
 }
 
+/// Clears the scissor rect of the mask pass when only part of the Voronoi textures is
+/// recomputed.
+#[derive(Resource)]
+pub struct MaskClearPipeline {
+    pub pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for MaskClearPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("mask_clear_pipeline".into()),
+                    layout: vec![],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: MASK_CLEAR_SHADER,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba16Float,
+                                blend: None,
+                                write_mask: ColorWrites::ALL,
+                            }),
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba32Uint,
+                                blend: None,
+                                write_mask: ColorWrites::ALL,
+                            }),
+                        ],
+                    }),
+                    push_constant_ranges: vec![],
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                    zero_initialize_workgroup_memory: false,
+                });
+
+        Self { pipeline }
+    }
+}
+
 pub struct MaskPhase {
     pub bin_key: MaskPhaseBinKey,
     pub representative_entity: (Entity, MainEntity),
@@ -178,33 +280,69 @@ pub type DrawMaskMesh = (
     DrawMesh2d,
 );
 
+/// Bind group of a material along with the inputs it was created from.
+pub struct MaskMaterialBindGroup {
+    pub bind_group: BindGroup,
+    uniform: MaskMaterialUniform,
+    texture_view: TextureViewId,
+    /// Keeps the uniform buffer alive as long as the bind group.
+    _buffer: UniformBuffer<MaskMaterialUniform>,
+}
+
 #[derive(Resource, Deref, DerefMut, Default)]
-pub struct MaskMaterialBindGroups(MainEntityHashMap<BindGroup>);
+pub struct MaskMaterialBindGroups(MainEntityHashMap<MaskMaterialBindGroup>);
 
 pub fn prepare_mask_material_bind_groups(
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
     pipeline: Res<MaskPipeline>,
     images: Res<RenderAssets<GpuImage>>,
     fallback_image: Res<FallbackImage>,
     flood_materials: Res<RenderVoronoiMaterials>,
     mut bind_groups: ResMut<MaskMaterialBindGroups>,
 ) {
-    // Only update bind groups for entities that have changed or are new
     bind_groups.retain(|entity, _| flood_materials.contains_key(entity));
 
-    for (entity, alpha_mask) in flood_materials.iter() {
-        let alpha_mask_image = if let Some(image) = images.get(*alpha_mask) {
+    for (entity, material) in flood_materials.iter() {
+        let alpha_mask_image = if let Some(image) = images.get(material.alpha_mask) {
             image
         } else {
             &fallback_image.d2
         };
-        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+
+        // Only update bind groups for entities that have changed or are new, a new texture
+        // view also catches alpha masks that finished loading
+        let texture_view = alpha_mask_image.texture_view.id();
+        if bind_groups.get(entity).is_some_and(|bind_group| {
+            bind_group.uniform == material.uniform && bind_group.texture_view == texture_view
+        }) {
+            continue;
+        }
+
+        let mut buffer = UniformBuffer::from(material.uniform);
+        buffer.write_buffer(&render_device, &render_queue);
+        let Some(binding) = buffer.binding() else {
+            continue;
+        };
+
         let bind_group = render_device.create_bind_group(
             "mask_material_bind_group",
             &pipeline.material_layout,
-            &BindGroupEntries::sequential((&alpha_mask_image.texture_view, &sampler)),
+            &BindGroupEntries::sequential((
+                &alpha_mask_image.texture_view,
+                &pipeline.sampler,
+                binding,
+            )),
+        );
+        bind_groups.insert(
+            *entity,
+            MaskMaterialBindGroup {
+                bind_group,
+                uniform: material.uniform,
+                texture_view,
+                _buffer: buffer,
+            },
         );
-        bind_groups.insert(*entity, bind_group);
     }
 }
 
@@ -226,7 +364,7 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetMaskMaterialBindGroup
         let Some(bind_group) = bind_groups.get(&item.main_entity()) else {
             return RenderCommandResult::Skip;
         };
-        pass.set_bind_group(I, &bind_group, &[]);
+        pass.set_bind_group(I, &bind_group.bind_group, &[]);
         RenderCommandResult::Success
     }
 }
@@ -236,8 +374,7 @@ pub fn run_mask_pass<'w>(
     render_context: &mut RenderContext<'w>,
     retained_view_entity: &RetainedViewEntity,
     view_entity: &Entity,
-    output: &CachedTexture,
-    camera: &ExtractedCamera,
+    voronoi_textures: &VoronoiTexture,
 ) {
     let Some(mask_phases) = world.get_resource::<ViewBinnedRenderPhases<MaskPhase>>() else {
         error!("MaskPhase not available");
@@ -251,16 +388,32 @@ pub fn run_mask_pass<'w>(
 
     let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
         label: Some("mask_pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-            view: &output.default_view,
-            resolve_target: None,
-            ops: Operations::default(),
-        })],
+        color_attachments: &[
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.output().default_view,
+                resolve_target: None,
+                ops: voronoi_textures.output_operations(),
+            }),
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.object_output().default_view,
+                resolve_target: None,
+                ops: voronoi_textures.output_operations(),
+            }),
+        ],
         ..default()
     });
 
-    if let Some(viewport) = camera.viewport.as_ref() {
-        pass.set_camera_viewport(viewport);
+    if voronoi_textures.scissor().is_some() {
+        let Some(clear_pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(world.resource::<MaskClearPipeline>().pipeline)
+        else {
+            return;
+        };
+
+        voronoi_textures.set_scissor(&mut pass);
+        pass.set_render_pipeline(clear_pipeline);
+        pass.draw(0..3, 0..1);
     }
 
     if !phase.is_empty() {
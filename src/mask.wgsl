@@ -1,15 +1,42 @@
 #import bevy_sprite::mesh2d_vertex_output::VertexOutput
+#import bevy_voronoi::mask::mask_coverage
+
+struct MaskMaterial {
+    id: u32,
+    multiplicative_weight: f32,
+    additive_weight: f32,
+    channel: u32,
+    flags: u32,
+    threshold: f32,
+    uv_transform: mat3x3<f32>,
+}
 
 @group(2) @binding(0) var alpha_texture: texture_2d<f32>;
 @group(2) @binding(1) var alpha_sampler: sampler;
+@group(2) @binding(2) var<uniform> material: MaskMaterial;
+
+struct FragmentOutput {
+    @location(0) mask: vec4<f32>,
+    @location(1) object: vec4<u32>,
+}
 
 @fragment
-fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    let mask = textureSample(alpha_texture, alpha_sampler, in.uv).a;
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    let uv = (material.uv_transform * vec3(in.uv, 1.0)).xy;
+    let color = textureSample(alpha_texture, alpha_sampler, uv);
+    let mask = mask_coverage(color, material.channel, material.flags, material.threshold);
 
     if mask <= 0. {
         discard;
     }
 
-    return vec4(mask);
+    var out: FragmentOutput;
+    out.mask = vec4(mask);
+    out.object = vec4(
+        material.id,
+        bitcast<u32>(material.multiplicative_weight),
+        bitcast<u32>(material.additive_weight),
+        0u,
+    );
+    return out;
 }
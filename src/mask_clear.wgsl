@@ -0,0 +1,10 @@
+struct FragmentOutput {
+    @location(0) mask: vec4<f32>,
+    @location(1) object: vec4<u32>,
+}
+
+// Clears the scissor rect of the mask pass, load operations ignore it
+@fragment
+fn fragment() -> FragmentOutput {
+    return FragmentOutput(vec4(0.0), vec4(0u));
+}
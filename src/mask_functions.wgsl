@@ -0,0 +1,31 @@
+#define_import_path bevy_voronoi::mask
+
+const MASK_FLAG_INVERT: u32 = 1u;
+const MASK_FLAG_THRESHOLD: u32 = 2u;
+
+// Coverage of `color` with the channel, invert and threshold settings of a
+// `VoronoiMaterial`. Anti-aliased across the threshold so the seed passes put the edge at
+// the threshold crossing.
+fn mask_coverage(color: vec4<f32>, channel: u32, flags: u32, threshold: f32) -> f32 {
+    // Same order as `MaskChannel`
+    var coverage = color.a;
+    switch channel {
+        case 0u: { coverage = color.r; }
+        case 1u: { coverage = color.g; }
+        case 2u: { coverage = color.b; }
+        case 4u: { coverage = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722)); }
+        default: {}
+    }
+
+    if (flags & MASK_FLAG_INVERT) != 0u {
+        coverage = 1.0 - coverage;
+    }
+
+    // Derivatives need uniform control flow, so take them before branching on the flags
+    let coverage_width = max(fwidth(coverage), 1e-4);
+    if (flags & MASK_FLAG_THRESHOLD) != 0u {
+        return saturate((coverage - threshold) / coverage_width + 0.5);
+    }
+
+    return coverage;
+}
@@ -1,41 +1,51 @@
+use std::{borrow::Cow, iter, ops::Range};
+
 use bevy::{
     asset::load_internal_asset,
-    core_pipeline::core_2d::{
-        graph::{Core2d, Node2d},
-        BatchSetKey2d,
+    core_pipeline::{
+        core_2d::{
+            graph::{Core2d, Node2d},
+            BatchSetKey2d,
+        },
+        tonemapping::{DebandDither, Tonemapping},
     },
     ecs::{
         entity::EntityHashMap,
-        query::QueryItem,
-        system::{lifetimeless::Read, SystemChangeTick},
+        query::{QueryItem, QueryState},
+        system::{lifetimeless::Read, SystemChangeTick, SystemParam},
     },
-    math::Affine3,
-    platform::collections::HashSet,
+    math::{Affine2, Affine3},
+    platform::collections::{HashMap, HashSet},
     prelude::*,
     render::{
         batching::{
             gpu_preprocessing::GpuPreprocessingMode,
             no_gpu_preprocessing::batch_and_prepare_binned_render_phase,
         },
-        camera::{extract_cameras, ExtractedCamera},
+        camera::{
+            extract_cameras, CameraRenderGraph, CameraUpdateSystem, RenderTarget, ScalingMode,
+        },
         extract_component::{ExtractComponent, ExtractComponentPlugin},
         mesh::RenderMesh,
+        primitives::Aabb,
         render_asset::{prepare_assets, RenderAssets},
         render_graph::{
-            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, RenderSubGraph,
+            ViewNode, ViewNodeRunner,
         },
         render_phase::{
-            AddRenderCommand, BinnedRenderPhaseType, DrawFunctions, InputUniformIndex,
-            ViewBinnedRenderPhases,
+            sweep_old_entities, AddRenderCommand, BinnedRenderPhaseType, DrawFunctions,
+            InputUniformIndex, TrackedRenderPass, ViewBinnedRenderPhases,
         },
         render_resource::{
-            Extent3d, PipelineCache, SpecializedMeshPipelines, TextureDescriptor, TextureDimension,
-            TextureFormat, TextureUsages,
+            Extent3d, LoadOp, Operations, Origin3d, PipelineCache, SpecializedComputePipelines,
+            SpecializedMeshPipelines, SpecializedRenderPipelines, StoreOp, TexelCopyTextureInfo,
+            TextureDescriptor, TextureDimension, TextureFormat, TextureId, TextureUsages,
         },
         renderer::{RenderContext, RenderDevice},
         sync_world::{MainEntity, MainEntityHashMap},
-        texture::{CachedTexture, TextureCache},
-        view::{ExtractedView, RenderVisibleEntities, RetainedViewEntity, ViewTarget},
+        texture::{CachedTexture, GpuImage, TextureCache},
+        view::{ExtractedView, RenderLayers, RenderVisibleEntities, RetainedViewEntity},
         Extract, Render, RenderApp, RenderSet,
     },
     sprite::{
@@ -46,19 +56,64 @@ use bevy::{
     utils::Parallel,
 };
 
-use crate::{flood::*, mask::*};
+use crate::{distance::*, flood::*, mask::*, readback::*, seed::*, sprite::*};
 
 pub struct Voronoi2dPlugin;
 impl Plugin for Voronoi2dPlugin {
     fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            MASK_FUNCTIONS_SHADER,
+            "mask_functions.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(app, MASK_SHADER, "mask.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, MASK_CLEAR_SHADER, "mask_clear.wgsl", Shader::from_wgsl);
         load_internal_asset!(app, FLOOD_SEED_SHADER, "flood_seed.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            FLOOD_FUNCTIONS_SHADER,
+            "flood_functions.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(app, FLOOD_SHADER, "flood.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            FLOOD_COMPUTE_SHADER,
+            "flood_compute.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(app, POINT_SEED_SHADER, "point_seed.wgsl", Shader::from_wgsl);
+        load_internal_asset!(app, DISTANCE_SHADER, "distance.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            SPRITE_MASK_SHADER,
+            "sprite_mask.wgsl",
+            Shader::from_wgsl
+        );
 
         app.add_plugins(ExtractComponentPlugin::<VoronoiMaterial>::default())
             .add_plugins(ExtractComponentPlugin::<VoronoiCamera>::default())
+            .add_plugins(ExtractComponentPlugin::<VoronoiLayers>::default())
+            .add_plugins(ExtractComponentPlugin::<VoronoiChannels>::default())
+            .add_plugins(ExtractComponentPlugin::<VoronoiReadbackImage>::default())
             .init_resource::<EntitiesNeedingSpecialization<VoronoiMaterial>>()
-            .add_systems(PostUpdate, check_entities_needing_specialization);
+            .add_systems(
+                PostUpdate,
+                (
+                    check_entities_needing_specialization,
+                    resize_voronoi_targets.after(CameraUpdateSystem),
+                    (prepare_voronoi_readbacks, resize_voronoi_readbacks)
+                        .chain()
+                        .after(CameraUpdateSystem),
+                    apply_voronoi_world_rects
+                        .before(CameraUpdateSystem)
+                        .before(TransformSystem::TransformPropagate),
+                    warn_unsupported_voronoi_settings,
+                ),
+            )
+            .add_observer(receive_voronoi_readback)
+            .add_observer(remove_voronoi_readback);
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -66,10 +121,16 @@ impl Plugin for Voronoi2dPlugin {
 
         render_app
             .init_resource::<SpecializedMeshPipelines<MaskPipeline>>()
+            .init_resource::<SpecializedRenderPipelines<FloodSeedPipeline>>()
+            .init_resource::<SpecializedRenderPipelines<FloodPipeline>>()
+            .init_resource::<SpecializedComputePipelines<FloodComputePipeline>>()
+            .init_resource::<SpecializedRenderPipelines<DistancePipeline>>()
             .init_resource::<EntitySpecializationTicks<VoronoiMaterial>>()
             .init_resource::<SpecializedMaterial2dPipelineCache<VoronoiMaterial>>()
             .init_resource::<ViewBinnedRenderPhases<MaskPhase>>()
             .init_resource::<RenderVoronoiMaterials>()
+            .init_resource::<RenderPointSeeds>()
+            .init_resource::<RenderVoronoiSprites>()
             .init_resource::<MaskMaterialBindGroups>()
             .init_resource::<DrawFunctions<MaskPhase>>()
             .init_resource::<ViewEntitiesRenderCache>()
@@ -80,6 +141,8 @@ impl Plugin for Voronoi2dPlugin {
                     (extract_camera_phases, extract_entities_needs_specialization)
                         .after(extract_cameras),
                     extract_flood_materials,
+                    extract_point_seeds,
+                    extract_voronoi_sprites,
                 ),
             )
             .add_systems(
@@ -88,10 +151,20 @@ impl Plugin for Voronoi2dPlugin {
                     specialize_mask_meshes
                         .in_set(RenderSet::PrepareMeshes)
                         .after(prepare_assets::<RenderMesh>),
+                    prepare_voronoi_channel_views.in_set(RenderSet::ManageViews),
                     queue_mask_meshes.in_set(RenderSet::QueueMeshes),
-                    (prepare_view_entities_render_cache, prepare_flood_textures)
+                    sweep_old_entities::<MaskPhase>.in_set(RenderSet::QueueSweep),
+                    (
+                        prepare_view_entities_render_cache,
+                        prepare_flood_textures.after(prepare_view_entities_render_cache),
+                        prepare_flood_pipelines,
+                    )
                         .in_set(RenderSet::Prepare),
-                    batch_and_prepare_binned_render_phase::<MaskPhase, Mesh2dPipeline>
+                    (
+                        batch_and_prepare_binned_render_phase::<MaskPhase, Mesh2dPipeline>,
+                        prepare_point_seeds,
+                        prepare_voronoi_sprites,
+                    )
                         .in_set(RenderSet::PrepareResources),
                     prepare_mask_material_bind_groups.in_set(RenderSet::PrepareBindGroups),
                 ),
@@ -104,7 +177,9 @@ impl Plugin for Voronoi2dPlugin {
                     FloodDrawPassLabel,
                     Node2d::MainTransparentPass,
                 ),
-            );
+            )
+            .add_render_sub_graph(Voronoi2d)
+            .add_render_graph_node::<ViewNodeRunner<FloodDrawNode>>(Voronoi2d, FloodDrawPassLabel);
     }
 
     fn finish(&self, app: &mut App) {
@@ -114,22 +189,31 @@ impl Plugin for Voronoi2dPlugin {
 
         render_app
             .init_resource::<MaskPipeline>()
-            .init_resource::<FloodPipeline>();
+            .init_resource::<MaskClearPipeline>()
+            .init_resource::<FloodSeedPipeline>()
+            .init_resource::<FloodPipeline>()
+            .init_resource::<PointSeedPipeline>()
+            .init_resource::<SpriteMaskPipeline>()
+            .init_resource::<DistancePipeline>();
+
+        let limits = render_app.world().resource::<RenderDevice>().limits();
+        if limits.max_storage_textures_per_shader_stage >= 2 {
+            render_app.init_resource::<FloodComputePipeline>();
+        }
     }
 }
 
+type NeedsSpecializationFilter = (
+    Or<(
+        Changed<Mesh2d>,
+        AssetChanged<Mesh2d>,
+        Changed<VoronoiMaterial>,
+    )>,
+    With<VoronoiMaterial>,
+);
+
 fn check_entities_needing_specialization(
-    needs_specialization: Query<
-        Entity,
-        (
-            Or<(
-                Changed<Mesh2d>,
-                AssetChanged<Mesh2d>,
-                Changed<VoronoiMaterial>,
-            )>,
-            With<VoronoiMaterial>,
-        ),
-    >,
+    needs_specialization: Query<Entity, NeedsSpecializationFilter>,
     mut par_local: Local<Parallel<Vec<Entity>>>,
     mut entities_needing_specialization: ResMut<EntitiesNeedingSpecialization<VoronoiMaterial>>,
 ) {
@@ -142,25 +226,501 @@ fn check_entities_needing_specialization(
     par_local.drain_into(&mut entities_needing_specialization);
 }
 
-#[derive(Component, ExtractComponent, Clone)]
+/// Render graph of [`VoronoiOffscreen`] cameras, running only the mask, seed, flood and
+/// distance passes.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderSubGraph)]
+pub struct Voronoi2d;
+
+/// Makes a [`VoronoiCamera`] skip the main scene and only run the [`Voronoi2d`] graph,
+/// e.g. to generate the field of a level region independently of what the player sees.
+///
+/// The camera should render into an image through `Camera::target`, which sets the size of
+/// the [`VoronoiTexture`] but is otherwise left untouched. A window target is left
+/// unwritten as well and logs a warning. Use [`VoronoiCamera::target`]
+/// or [`VoronoiReadback`] to consume the field in the main world.
+#[derive(Component, Clone, Copy, Default, Debug)]
+#[require(Camera2d, VoronoiCamera, CameraRenderGraph::new(Voronoi2d), Msaa::Off)]
+pub struct VoronoiOffscreen;
+
+/// How the jump flood steps are executed.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum FloodMode {
+    /// A full-screen fragment pass per step.
+    #[default]
+    Fragment,
+    /// A single compute pass dispatching every step, falls back to [`FloodMode::Fragment`]
+    /// when storage textures are not supported.
+    Compute,
+}
+
+#[derive(Component, ExtractComponent, Clone, PartialEq)]
 pub struct VoronoiCamera {
+    /// Size of the [`VoronoiTexture`] relative to the camera target, ignored when
+    /// `resolution` is set.
     pub scale: f32,
+    /// Explicit size of the [`VoronoiTexture`] in texels, independent of the window size.
+    /// Flood distances are measured in texels, so its aspect ratio should match the area
+    /// covered by the camera.
+    pub resolution: Option<UVec2>,
+    /// World space region covered by a [`VoronoiOffscreen`] camera, whose projection and
+    /// translation are kept in sync with it. Ignored with a warning on cameras rendering
+    /// the scene.
+    pub world_rect: Option<Rect>,
+    pub flood_mode: FloodMode,
+    pub jfa_schedule: JfaSchedule,
+    pub metric: DistanceMetric,
+    pub seed_mode: SeedMode,
+    /// Seeds further than this many texels of the [`VoronoiTexture`] are ignored and
+    /// the pixels without a seed in range are marked as far. Replaces `jfa_schedule`,
+    /// with a warning when that is not the default, by starting the flood at the next
+    /// power of two above it. Also lets changes only recompute the texels within this
+    /// distance of what changed.
+    pub max_distance: Option<f32>,
+    /// Spreads the flood over several frames, running at most this many flood passes per
+    /// frame, at least one. The previous field stays in [`VoronoiTexture::result`] until
+    /// the new one completes, see [`VoronoiFloodProgress`]. Changes during a flood start
+    /// a new one once it completes, changes of these settings or of the texture size
+    /// restart it right away.
+    pub flood_passes_per_frame: Option<u32>,
+    /// Also tracks the nearest seed of another object through the flood, for cell borders
+    /// and the distance to the cell edge, see [`VoronoiTexture::second_result`] and the
+    /// `cell_borders` example. Always uses [`FloodMode::Fragment`], warning when
+    /// `flood_mode` asks for [`FloodMode::Compute`].
+    pub second_seed: bool,
+    /// Resolves the flood output into a signed distance in world units every time it is
+    /// recomputed, see [`VoronoiDistanceTexture`].
+    pub distance_field: bool,
+    /// Image the final flood output is copied into every time it is recomputed, so it
+    /// can be bound by regular materials and sprites or read back. The image is resized
+    /// and converted to `Rgba16Float` to match the `VoronoiTexture`.
+    pub target: Option<Handle<Image>>,
+    /// Only entities whose [`VoronoiLayers`] intersect these layers feed the diagram.
+    pub layers: RenderLayers,
 }
 
 impl Default for VoronoiCamera {
     fn default() -> Self {
-        Self { scale: 0.5 }
+        Self {
+            scale: 0.5,
+            resolution: None,
+            world_rect: None,
+            flood_mode: FloodMode::default(),
+            jfa_schedule: JfaSchedule::default(),
+            metric: DistanceMetric::default(),
+            seed_mode: SeedMode::default(),
+            max_distance: None,
+            flood_passes_per_frame: None,
+            second_seed: false,
+            distance_field: false,
+            target: None,
+            layers: RenderLayers::default(),
+        }
+    }
+}
+
+impl VoronoiCamera {
+    pub fn with_resolution(mut self, resolution: UVec2) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn with_world_rect(mut self, world_rect: Rect) -> Self {
+        self.world_rect = Some(world_rect);
+        self
+    }
+
+    pub fn with_flood_mode(mut self, flood_mode: FloodMode) -> Self {
+        self.flood_mode = flood_mode;
+        self
+    }
+
+    pub fn with_jfa_schedule(mut self, jfa_schedule: JfaSchedule) -> Self {
+        self.jfa_schedule = jfa_schedule;
+        self
+    }
+
+    pub fn with_metric(mut self, metric: DistanceMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    pub fn with_seed_mode(mut self, seed_mode: SeedMode) -> Self {
+        self.seed_mode = seed_mode;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
+
+    pub fn with_flood_passes_per_frame(mut self, passes: u32) -> Self {
+        self.flood_passes_per_frame = Some(passes.max(1));
+        self
+    }
+
+    pub fn with_second_seed(mut self) -> Self {
+        self.second_seed = true;
+        self
+    }
+
+    pub fn with_distance_field(mut self) -> Self {
+        self.distance_field = true;
+        self
+    }
+
+    pub fn with_target(mut self, target: Handle<Image>) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn with_layers(mut self, layers: RenderLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub(crate) fn includes(&self, layers: Option<&VoronoiLayers>) -> bool {
+        match layers {
+            Some(layers) => self.layers.intersects(layers),
+            None => self.layers.intersects(&RenderLayers::default()),
+        }
+    }
+
+    pub fn texture_size(&self, target_size: UVec2) -> UVec2 {
+        self.resolution
+            .unwrap_or_else(|| (target_size.as_vec2() * self.scale).as_uvec2())
+    }
+
+    /// Settings of every flood pass for a target of the given size.
+    pub fn flood_passes(&self, size: UVec2) -> Vec<FloodUniform> {
+        self.jfa_schedule
+            .with_max_distance(self.max_distance)
+            .steps(size)
+            .into_iter()
+            .map(|step| FloodUniform {
+                step,
+                max_distance: self.max_distance.unwrap_or(f32::MAX),
+                minkowski_p: self.metric.minkowski_p(),
+            })
+            .collect()
     }
 }
 
+/// Additional named Voronoi fields computed from the view of a [`VoronoiCamera`] without
+/// rendering the scene again, each with its own settings and set of entities selected
+/// by [`VoronoiCamera::layers`].
+///
+/// Each channel gets a render world view listed in [`VoronoiChannelViews`] holding its
+/// [`VoronoiTexture`]. The `world_rect` of a channel is ignored, the field covering the
+/// view of the camera, and [`VoronoiReadback`] only reads the field of the camera. Both
+/// log a warning when the channels change.
+#[derive(Component, ExtractComponent, Clone, Default)]
+#[require(VoronoiCamera)]
+pub struct VoronoiChannels(pub Vec<VoronoiChannel>);
+
+impl VoronoiChannels {
+    pub fn with_channel(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        voronoi_camera: VoronoiCamera,
+    ) -> Self {
+        self.0.push(VoronoiChannel {
+            name: name.into(),
+            voronoi_camera,
+        });
+        self
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct VoronoiChannel {
+    pub name: Cow<'static, str>,
+    pub voronoi_camera: VoronoiCamera,
+}
+
+/// Render world views of the [`VoronoiChannels`] of a camera, each holding the
+/// [`VoronoiTexture`], [`VoronoiDistanceTexture`] and [`VoronoiFloodProgress`] of its
+/// channel.
+#[derive(Component, Clone, Default)]
+pub struct VoronoiChannelViews(Vec<(Cow<'static, str>, Entity)>);
+
+impl VoronoiChannelViews {
+    /// Render world view of the channel named `name`.
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.0
+            .iter()
+            .find(|(channel, _)| channel == name)
+            .map(|(_, entity)| *entity)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.0.iter().map(|(name, entity)| (name.as_ref(), *entity))
+    }
+}
+
+fn apply_voronoi_world_rects(
+    mut cameras: Query<(&VoronoiCamera, &mut Projection, &mut Transform), With<VoronoiOffscreen>>,
+) {
+    for (voronoi_camera, mut projection, mut transform) in &mut cameras {
+        let Some(world_rect) = voronoi_camera.world_rect else {
+            continue;
+        };
+
+        let (width, height) = (world_rect.width(), world_rect.height());
+
+        // Only touch the projection when it differs to keep change detection quiet
+        let is_synced = matches!(
+            projection.as_ref(),
+            Projection::Orthographic(OrthographicProjection {
+                scale: 1.0,
+                viewport_origin: Vec2 { x: 0.5, y: 0.5 },
+                scaling_mode: ScalingMode::Fixed { width: w, height: h },
+                ..
+            }) if *w == width && *h == height
+        );
+
+        if !is_synced {
+            let mut orthographic = match projection.as_ref() {
+                Projection::Orthographic(orthographic) => orthographic.clone(),
+                _ => OrthographicProjection::default_2d(),
+            };
+            orthographic.scale = 1.0;
+            orthographic.viewport_origin = Vec2::splat(0.5);
+            orthographic.scaling_mode = ScalingMode::Fixed { width, height };
+            *projection = Projection::Orthographic(orthographic);
+        }
+
+        let translation = world_rect.center().extend(transform.translation.z);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
+
+/// Warns once per change about settings of a camera or its channels that fall back to
+/// something else.
+fn warn_unsupported_voronoi_settings(
+    cameras: Query<(Entity, Ref<VoronoiCamera>, Option<Ref<VoronoiChannels>>)>,
+    readbacks: Query<(), With<VoronoiReadback>>,
+    offscreen_cameras: Query<(Entity, Ref<Camera>), With<VoronoiOffscreen>>,
+) {
+    for (entity, camera) in &offscreen_cameras {
+        if camera.is_changed() && matches!(camera.target, RenderTarget::Window(_)) {
+            warn!(
+                "VoronoiOffscreen camera {entity} targets a window, which it never draws to, \
+                 render it into an image through Camera::target instead"
+            );
+        }
+    }
+
+    for (entity, voronoi_camera, channels) in &cameras {
+        if !voronoi_camera.is_changed() && !channels.as_ref().is_some_and(Ref::is_changed) {
+            continue;
+        }
+
+        if voronoi_camera.world_rect.is_some() && !offscreen_cameras.contains(entity) {
+            warn!(
+                "VoronoiCamera::world_rect of camera {entity} is ignored without \
+                 VoronoiOffscreen, the camera keeps its own projection"
+            );
+        }
+
+        let channels = channels.iter().flat_map(|channels| &channels.0);
+
+        for voronoi_camera in iter::once(&*voronoi_camera)
+            .chain(channels.clone().map(|channel| &channel.voronoi_camera))
+        {
+            if voronoi_camera.second_seed && voronoi_camera.flood_mode == FloodMode::Compute {
+                warn!(
+                    "VoronoiCamera::second_seed is not supported by FloodMode::Compute, \
+                     camera {entity} uses FloodMode::Fragment"
+                );
+            }
+            if voronoi_camera.max_distance.is_some()
+                && voronoi_camera.jfa_schedule != JfaSchedule::default()
+            {
+                warn!(
+                    "VoronoiCamera::jfa_schedule of camera {entity} is ignored with max_distance \
+                     set, the flood uses JfaSchedule::Capped at max_distance"
+                );
+            }
+        }
+
+        for channel in channels {
+            if channel.voronoi_camera.world_rect.is_some() {
+                warn!(
+                    "VoronoiChannel `{}` of camera {entity} sets a world_rect, channels always \
+                     cover the view of the camera",
+                    channel.name
+                );
+            }
+            if readbacks.contains(entity) {
+                warn!(
+                    "VoronoiChannel `{}` of camera {entity} is not read back, VoronoiReadback \
+                     only reads the field of the camera",
+                    channel.name
+                );
+            }
+        }
+    }
+}
+
+fn resize_voronoi_targets(
+    cameras: Query<(&Camera, &VoronoiCamera, Option<&VoronoiChannels>)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let voronoi_cameras =
+        cameras
+            .iter()
+            .flat_map(|(camera, voronoi_camera, channels)| {
+                iter::once(voronoi_camera)
+                    .chain(channels.into_iter().flat_map(|channels| {
+                        channels.0.iter().map(|channel| &channel.voronoi_camera)
+                    }))
+                    .map(move |voronoi_camera| (camera, voronoi_camera))
+            });
+
+    for (camera, voronoi_camera) in voronoi_cameras {
+        let (Some(target), Some(target_size)) = (
+            voronoi_camera.target.as_ref(),
+            camera.physical_viewport_size(),
+        ) else {
+            continue;
+        };
+
+        let size = voronoi_camera.texture_size(target_size);
+        let size = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+
+        let Some(image) = images.get(target) else {
+            continue;
+        };
+
+        let usage =
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC;
+
+        if image.texture_descriptor.size == size
+            && image.texture_descriptor.format == TextureFormat::Rgba16Float
+            && image.texture_descriptor.usage.contains(usage)
+        {
+            continue;
+        }
+
+        let Some(image) = images.get_mut(target) else {
+            continue;
+        };
+
+        image.texture_descriptor.format = TextureFormat::Rgba16Float;
+        image.texture_descriptor.usage |= usage;
+        image.data = None;
+        image.resize(size);
+    }
+}
+
+/// Adds a `Mesh2d` or a `Sprite` to the mask pass.
+///
+/// Sprites are masked as a single quad honouring texture atlases, `rect`, flips,
+/// `custom_size` and the anchor. Their `image_mode` is ignored, so sliced and tiled
+/// sprites are masked as if their image was stretched over the whole sprite.
 #[derive(Component, ExtractComponent, Clone, Default)]
 pub struct VoronoiMaterial {
+    /// Mask sampled over the mesh UVs. Ignored on sprites, which read their own image.
     pub alpha_mask: Handle<Image>,
+    /// Transform applied to the mesh UVs before sampling `alpha_mask`, e.g. to select a
+    /// frame of a sprite sheet. Ignored on sprites.
+    pub uv_transform: Affine2,
+    /// Object id propagated alongside the nearest seed, see
+    /// [`VoronoiTexture::object_result`]. Defaults to the entity index.
+    pub id: Option<u32>,
+    pub weight: VoronoiWeight,
+    /// Channel of `alpha_mask` read as coverage.
+    pub channel: MaskChannel,
+    /// Reads `1 - coverage` instead.
+    pub invert: bool,
+    /// Coverage above which pixels are inside the mask. The mask is anti-aliased across
+    /// the threshold so the seeds are placed at the threshold crossing. Without a
+    /// threshold the coverage is used as is and only zero coverage is discarded.
+    pub threshold: Option<f32>,
 }
 
 impl VoronoiMaterial {
     pub fn new(alpha_mask: Handle<Image>) -> Self {
-        Self { alpha_mask }
+        Self {
+            alpha_mask,
+            ..default()
+        }
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: VoronoiWeight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_channel(mut self, channel: MaskChannel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn inverted(mut self) -> Self {
+        self.invert = true;
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    pub fn with_uv_transform(mut self, uv_transform: Affine2) -> Self {
+        self.uv_transform = uv_transform;
+        self
+    }
+
+    /// Maps the mesh UVs onto `rect`, given in normalized texture coordinates.
+    pub fn with_uv_rect(self, rect: Rect) -> Self {
+        self.with_uv_transform(
+            Affine2::from_translation(rect.min) * Affine2::from_scale(rect.size()),
+        )
+    }
+
+    /// Maps the mesh UVs onto the frame `index` of an atlas packed `alpha_mask`.
+    pub fn with_atlas_frame(self, layout: &TextureAtlasLayout, index: usize) -> Self {
+        let Some(frame) = layout.textures.get(index) else {
+            warn!("Frame {index} is out of range of the texture atlas");
+            return self;
+        };
+
+        let size = layout.size.as_vec2();
+        let frame = frame.as_rect();
+        self.with_uv_rect(Rect::from_corners(frame.min / size, frame.max / size))
+    }
+
+    pub(crate) fn uniform(&self, default_id: u32) -> MaskMaterialUniform {
+        let mut flags = 0;
+        if self.invert {
+            flags |= MASK_FLAG_INVERT;
+        }
+        if self.threshold.is_some() {
+            flags |= MASK_FLAG_THRESHOLD;
+        }
+
+        MaskMaterialUniform {
+            id: self.id.unwrap_or(default_id),
+            multiplicative_weight: self.weight.clamped().multiplicative,
+            additive_weight: self.weight.additive,
+            channel: self.channel as u32,
+            flags,
+            threshold: self.threshold.unwrap_or_default(),
+            uv_transform: self.uv_transform.into(),
+        }
     }
 }
 
@@ -176,25 +736,52 @@ impl From<&VoronoiMaterial> for AssetId<Image> {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub struct RenderVoronoiMaterial {
+    pub alpha_mask: AssetId<Image>,
+    pub uniform: MaskMaterialUniform,
+    /// Local bounds of the mesh, used to limit recomputations to the area it covers.
+    pub aabb: Option<Aabb>,
+}
+
+/// Voronoi cameras an entity with a [`VoronoiMaterial`] or [`VoronoiSeed`] feeds, see
+/// [`VoronoiCamera::layers`]. Independent of the `RenderLayers` the entity is drawn with,
+/// entities without it are on layer `0`.
+#[derive(Component, ExtractComponent, Clone, Default, PartialEq, Eq, Debug, Deref, DerefMut)]
+pub struct VoronoiLayers(pub RenderLayers);
+
+impl VoronoiLayers {
+    pub fn layer(n: usize) -> Self {
+        Self(RenderLayers::layer(n))
+    }
+}
+
 #[derive(Resource, Deref, DerefMut, Default)]
-pub struct RenderVoronoiMaterials(MainEntityHashMap<AssetId<Image>>);
+pub struct RenderVoronoiMaterials(MainEntityHashMap<RenderVoronoiMaterial>);
+
+type CameraPhaseQuery = (Entity, Read<Camera>, Option<Read<VoronoiChannels>>);
+type VoronoiCameraFilter = (With<Camera2d>, With<VoronoiCamera>);
 
 fn extract_camera_phases(
-    cameras: Extract<Query<(Entity, &Camera), (With<Camera2d>, With<VoronoiCamera>)>>,
+    cameras: Extract<Query<CameraPhaseQuery, VoronoiCameraFilter>>,
     mut flood_phases: ResMut<ViewBinnedRenderPhases<MaskPhase>>,
     mut live_entities: Local<HashSet<RetainedViewEntity>>,
 ) {
     live_entities.clear();
 
-    for (entity, camera) in &cameras {
+    for (entity, camera, channels) in &cameras {
         if !camera.is_active {
             continue;
         }
 
-        let retained_view_entity = RetainedViewEntity::new(entity.into(), None, 0);
+        // Channels are subviews of the camera, see `prepare_voronoi_channel_views`
+        let channel_count = channels.map_or(0, |channels| channels.0.len() as u32);
+        for subview_index in 0..=channel_count {
+            let retained_view_entity = RetainedViewEntity::new(entity.into(), None, subview_index);
 
-        flood_phases.prepare_for_new_frame(retained_view_entity, GpuPreprocessingMode::None);
-        live_entities.insert(retained_view_entity);
+            flood_phases.prepare_for_new_frame(retained_view_entity, GpuPreprocessingMode::None);
+            live_entities.insert(retained_view_entity);
+        }
     }
 
     // Clear out all dead views
@@ -225,16 +812,182 @@ fn extract_entities_needs_specialization(
     }
 }
 
+type FloodMaterialQuery = (
+    Entity,
+    Read<ViewVisibility>,
+    Read<VoronoiMaterial>,
+    Option<Read<Aabb>>,
+);
+
 fn extract_flood_materials(
     mut render_voronoi_instances: ResMut<RenderVoronoiMaterials>,
-    query: Extract<Query<(Entity, &ViewVisibility, &VoronoiMaterial), With<Mesh2d>>>,
+    query: Extract<Query<FloodMaterialQuery, With<Mesh2d>>>,
 ) {
     render_voronoi_instances.clear();
 
-    for (entity, view_visibility, material) in &query {
+    for (entity, view_visibility, material, aabb) in &query {
         if view_visibility.get() {
-            render_voronoi_instances.insert(entity.into(), material.into());
+            render_voronoi_instances.insert(
+                entity.into(),
+                RenderVoronoiMaterial {
+                    alpha_mask: material.into(),
+                    uniform: material.uniform(entity.index()),
+                    aabb: aabb.copied(),
+                },
+            );
+        }
+    }
+}
+
+type PointSeedQuery = (
+    Entity,
+    Read<InheritedVisibility>,
+    Read<GlobalTransform>,
+    Read<VoronoiSeed>,
+    Option<Read<VoronoiLayers>>,
+);
+
+fn extract_point_seeds(
+    mut point_seeds: ResMut<RenderPointSeeds>,
+    query: Extract<Query<PointSeedQuery>>,
+) {
+    point_seeds.seeds.clear();
+
+    for (entity, inherited_visibility, transform, seed, layers) in &query {
+        if inherited_visibility.get() {
+            point_seeds.seeds.push(RenderPointSeed {
+                vertex: PointSeedVertex {
+                    position: transform.translation(),
+                    id: seed.id.unwrap_or(entity.index()),
+                    weight: Vec2::new(seed.weight.clamped().multiplicative, seed.weight.additive),
+                },
+                layers: layers.cloned().unwrap_or_default().0,
+            });
+        }
+    }
+}
+
+type VoronoiSpriteQuery = (
+    Entity,
+    Read<ViewVisibility>,
+    Read<GlobalTransform>,
+    Read<Sprite>,
+    Read<VoronoiMaterial>,
+    Option<Read<VoronoiLayers>>,
+);
+
+fn extract_voronoi_sprites(
+    mut voronoi_sprites: ResMut<RenderVoronoiSprites>,
+    query: Extract<Query<VoronoiSpriteQuery>>,
+    images: Extract<Res<Assets<Image>>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+) {
+    voronoi_sprites.sprites.clear();
+
+    for (entity, view_visibility, transform, sprite, material, layers) in &query {
+        if !view_visibility.get() {
+            continue;
+        }
+
+        let Some(image) = images.get(&sprite.image) else {
+            continue;
+        };
+
+        voronoi_sprites.sprites.push(RenderVoronoiSprite {
+            image: sprite.image.id(),
+            instance: SpriteMaskInstance::new(
+                sprite,
+                transform,
+                image.size_f32(),
+                &texture_atlases,
+                material.uniform(entity.index()),
+            ),
+            layers: layers.cloned().unwrap_or_default().0,
+        });
+    }
+}
+
+/// Spawns a render world view for every [`VoronoiChannel`] of a camera, sharing its
+/// projection and visible entities, and despawns the views of removed channels.
+type ChannelCameraQuery = (
+    Entity,
+    Read<MainEntity>,
+    Read<ExtractedView>,
+    Read<RenderVisibleEntities>,
+    Read<Msaa>,
+    Option<Read<Tonemapping>>,
+    Option<Read<DebandDither>>,
+    Read<VoronoiChannels>,
+);
+
+fn prepare_voronoi_channel_views(
+    mut commands: Commands,
+    cameras: Query<ChannelCameraQuery>,
+    removed_channels: Query<Entity, (With<VoronoiChannelViews>, Without<VoronoiChannels>)>,
+    mut channel_views: Local<EntityHashMap<Vec<Entity>>>,
+) {
+    let mut current_channel_views = EntityHashMap::default();
+
+    for (entity, main_entity, view, visible_entities, msaa, tonemapping, dither, channels) in
+        &cameras
+    {
+        let mut views = channel_views.remove(&entity).unwrap_or_default();
+        for removed_view in views.drain(channels.0.len().min(views.len())..) {
+            commands.entity(removed_view).despawn();
+        }
+        views.resize_with(channels.0.len(), || commands.spawn_empty().id());
+
+        for (index, (channel, channel_view)) in channels.0.iter().zip(&views).enumerate() {
+            // The view key of the mask pipelines is cached per main entity, so it has to
+            // match the camera
+            let mut channel_commands = commands.entity(*channel_view);
+            channel_commands.insert((
+                ExtractedView {
+                    retained_view_entity: RetainedViewEntity::new(
+                        *main_entity,
+                        None,
+                        index as u32 + 1,
+                    ),
+                    clip_from_view: view.clip_from_view,
+                    world_from_view: view.world_from_view,
+                    clip_from_world: view.clip_from_world,
+                    hdr: view.hdr,
+                    viewport: view.viewport,
+                    color_grading: view.color_grading.clone(),
+                },
+                visible_entities.clone(),
+                *msaa,
+                Camera2d,
+                *main_entity,
+                channel.voronoi_camera.clone(),
+            ));
+            if let Some(tonemapping) = tonemapping {
+                channel_commands.insert(*tonemapping);
+            }
+            if let Some(dither) = dither {
+                channel_commands.insert(*dither);
+            }
         }
+
+        commands.entity(entity).insert(VoronoiChannelViews(
+            channels
+                .0
+                .iter()
+                .map(|channel| channel.name.clone())
+                .zip(views.iter().copied())
+                .collect(),
+        ));
+        current_channel_views.insert(entity, views);
+    }
+
+    // Views of cameras that were removed or deactivated
+    for removed_view in channel_views.values().flatten() {
+        commands.entity(*removed_view).despawn();
+    }
+    *channel_views = current_channel_views;
+
+    for entity in &removed_channels {
+        commands.entity(entity).remove::<VoronoiChannelViews>();
     }
 }
 
@@ -242,32 +995,190 @@ fn extract_flood_materials(
 pub struct ViewEntityRenderState {
     pub camera_viewport: UVec4,
     pub camera_transform: GlobalTransform,
+    pub voronoi_camera: VoronoiCamera,
+    pub target_size: Option<Extent3d>,
+    pub readback_size: Option<Extent3d>,
     pub entity_transforms: EntityHashMap<Affine3>,
-    pub material_assets: EntityHashMap<AssetId<Image>>,
+    pub materials: EntityHashMap<RenderVoronoiMaterial>,
+    pub point_seeds: Vec<PointSeedVertex>,
+    pub sprites: Vec<RenderVoronoiSprite>,
+    pub clip_from_world: Mat4,
+    pub texture_size: UVec2,
     pub has_changed: bool,
+    /// Texels of the Voronoi textures affected by the changes when they don't require a
+    /// full recomputation, see [`VoronoiCamera::max_distance`].
+    pub dirty_rect: Option<URect>,
+}
+
+impl ViewEntityRenderState {
+    /// World space bounds of a mesh, `None` when it has no [`Aabb`].
+    fn mesh_bounds(&self, entity: &Entity) -> Option<Rect> {
+        let transform = self.entity_transforms.get(entity)?;
+        let aabb = self.materials.get(entity)?.aabb?;
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+
+        let mut bounds = Rect::EMPTY;
+        for corner in [
+            min,
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            max,
+        ] {
+            bounds = bounds.union_point((transform.matrix3 * corner + transform.translation).xy());
+        }
+        Some(bounds)
+    }
+
+    /// Texels covered by the world space `bounds` expanded by `margin` texels.
+    fn texel_rect(&self, bounds: Rect, margin: f32) -> URect {
+        let size = self.texture_size.as_vec2();
+
+        let mut rect = Rect::EMPTY;
+        for corner in [
+            bounds.min,
+            Vec2::new(bounds.max.x, bounds.min.y),
+            Vec2::new(bounds.min.x, bounds.max.y),
+            bounds.max,
+        ] {
+            let ndc = self.clip_from_world.project_point3(corner.extend(0.0));
+            rect = rect.union_point((ndc.xy() * Vec2::new(0.5, -0.5) + 0.5) * size);
+        }
+
+        let rect = rect
+            .inflate(margin)
+            .intersect(Rect::from_corners(Vec2::ZERO, size));
+        if rect.is_empty() {
+            return URect::default();
+        }
+        URect::from_corners(rect.min.floor().as_uvec2(), rect.max.ceil().as_uvec2())
+    }
 }
 
 #[derive(Resource, Default, Deref, DerefMut)]
-pub struct ViewEntitiesRenderCache(MainEntityHashMap<ViewEntityRenderState>);
+pub struct ViewEntitiesRenderCache(HashMap<RetainedViewEntity, ViewEntityRenderState>);
 
 impl ViewEntitiesRenderCache {
     pub fn update(
         &mut self,
-        view_entity: &MainEntity,
+        view_entity: &RetainedViewEntity,
         asset_server: &AssetServer,
         mut new_state: ViewEntityRenderState,
     ) {
         if !self.contains_key(view_entity)
             || self.has_state_changed(view_entity, &new_state, asset_server)
         {
-            new_state.has_changed = true;
+            if !new_state.has_changed {
+                new_state.dirty_rect = self.dirty_rect(view_entity, &new_state, asset_server);
+            }
+            // An empty rect means none of the changes are drawn by the view
+            new_state.has_changed = new_state.dirty_rect.is_none_or(|rect| !rect.is_empty());
         }
         self.insert(*view_entity, new_state);
     }
 
+    /// Texels affected by the changes since the previous state, the union of the old and
+    /// new bounds of everything that changed expanded by [`VoronoiCamera::max_distance`],
+    /// beyond which a seed has no influence. `None` when the whole texture has to be
+    /// recomputed.
+    fn dirty_rect(
+        &self,
+        view_entity: &RetainedViewEntity,
+        new: &ViewEntityRenderState,
+        asset_server: &AssetServer,
+    ) -> Option<URect> {
+        let current = self.get(view_entity)?;
+        let max_distance = new.voronoi_camera.max_distance?;
+
+        if self.has_view_changed(current, new)
+            || new.materials.values().any(|material| {
+                asset_server
+                    .get_load_state(material.alpha_mask)
+                    .is_some_and(|state| !state.is_loaded())
+            })
+        {
+            return None;
+        }
+
+        let mut bounds = Rect::EMPTY;
+
+        for entity in current.materials.keys().chain(new.materials.keys()) {
+            let transform_changed = match (
+                current.entity_transforms.get(entity),
+                new.entity_transforms.get(entity),
+            ) {
+                (Some(current_transform), Some(new_transform)) => {
+                    current_transform.matrix3 != new_transform.matrix3
+                        || current_transform.translation != new_transform.translation
+                }
+                _ => true,
+            };
+            if !transform_changed && current.materials.get(entity) == new.materials.get(entity) {
+                continue;
+            }
+
+            for state in [current, new] {
+                if state.materials.contains_key(entity) {
+                    bounds = bounds.union(state.mesh_bounds(entity)?);
+                }
+            }
+        }
+
+        let changed_sprites = current
+            .sprites
+            .iter()
+            .filter(|sprite| !new.sprites.contains(sprite))
+            .chain(
+                new.sprites
+                    .iter()
+                    .filter(|sprite| !current.sprites.contains(sprite)),
+            );
+        for sprite in changed_sprites {
+            let SpriteMaskInstance {
+                origin,
+                x_axis,
+                y_axis,
+                ..
+            } = sprite.instance;
+            for corner in [
+                origin,
+                origin + x_axis,
+                origin + y_axis,
+                origin + x_axis + y_axis,
+            ] {
+                bounds = bounds.union_point(corner.xy());
+            }
+        }
+
+        let changed_point_seeds = current
+            .point_seeds
+            .iter()
+            .filter(|seed| !new.point_seeds.contains(seed))
+            .chain(
+                new.point_seeds
+                    .iter()
+                    .filter(|seed| !current.point_seeds.contains(seed)),
+            );
+        for seed in changed_point_seeds {
+            bounds = bounds.union_point(seed.position.xy());
+        }
+
+        // Only entities the view doesn't draw changed
+        if bounds.min.x > bounds.max.x {
+            return Some(URect::default());
+        }
+
+        // The seed pass looks at the neighbours of each texel and moves seeds by up to a
+        // texel
+        Some(new.texel_rect(bounds, max_distance + 2.0))
+    }
+
     fn has_state_changed(
         &self,
-        view_entity: &MainEntity,
+        view_entity: &RetainedViewEntity,
         new_state: &ViewEntityRenderState,
         asset_server: &AssetServer,
     ) -> bool {
@@ -280,15 +1191,28 @@ impl ViewEntitiesRenderCache {
             || self.have_materials_changed(current_state, new_state, asset_server)
     }
 
-    fn has_basic_state_changed(
+    fn has_view_changed(
         &self,
         current: &ViewEntityRenderState,
         new: &ViewEntityRenderState,
     ) -> bool {
         current.camera_viewport != new.camera_viewport
             || current.camera_transform != new.camera_transform
+            || current.voronoi_camera != new.voronoi_camera
+            || current.target_size != new.target_size
+            || current.readback_size != new.readback_size
+    }
+
+    fn has_basic_state_changed(
+        &self,
+        current: &ViewEntityRenderState,
+        new: &ViewEntityRenderState,
+    ) -> bool {
+        self.has_view_changed(current, new)
             || current.entity_transforms.len() != new.entity_transforms.len()
-            || current.material_assets.len() != new.material_assets.len()
+            || current.materials.len() != new.materials.len()
+            || current.point_seeds != new.point_seeds
+            || current.sprites != new.sprites
     }
 
     fn have_transforms_changed(
@@ -317,16 +1241,14 @@ impl ViewEntitiesRenderCache {
         new: &ViewEntityRenderState,
         asset_server: &AssetServer,
     ) -> bool {
-        for (entity, alpha_image) in &new.material_assets {
-            if let Some(asset_state) = asset_server.get_load_state(alpha_image.clone()) {
+        for (entity, material) in &new.materials {
+            if let Some(asset_state) = asset_server.get_load_state(material.alpha_mask) {
                 if !asset_state.is_loaded() {
                     return true;
                 }
             }
 
-            if !current.material_assets.contains_key(entity)
-                || current.material_assets.get(entity).unwrap() != alpha_image
-            {
+            if current.materials.get(entity) != Some(material) {
                 return true;
             }
         }
@@ -335,44 +1257,103 @@ impl ViewEntitiesRenderCache {
     }
 }
 
+/// Extracted meshes, sprites and point seeds drawn into the mask and seed textures.
+#[derive(SystemParam)]
+struct VoronoiRenderInputs<'w, 's> {
+    render_voronoi_instances: Res<'w, RenderVoronoiMaterials>,
+    render_mesh_instances: Res<'w, RenderMesh2dInstances>,
+    point_seeds: Res<'w, RenderPointSeeds>,
+    voronoi_sprites: Res<'w, RenderVoronoiSprites>,
+    voronoi_layers: Query<'w, 's, &'static VoronoiLayers>,
+}
+
 fn prepare_view_entities_render_cache(
-    render_voronoi_instances: Res<RenderVoronoiMaterials>,
-    views: Query<(&MainEntity, &ExtractedView, &RenderVisibleEntities)>,
+    inputs: VoronoiRenderInputs,
+    views: Query<(
+        &ExtractedView,
+        &RenderVisibleEntities,
+        &VoronoiCamera,
+        Option<&VoronoiReadbackImage>,
+    )>,
     mask_render_phases: Res<ViewBinnedRenderPhases<MaskPhase>>,
-    render_mesh_instances: Res<RenderMesh2dInstances>,
     mut view_entities_render_cache: ResMut<ViewEntitiesRenderCache>,
     asset_server: Res<AssetServer>,
+    images: Res<RenderAssets<GpuImage>>,
 ) {
-    if render_voronoi_instances.is_empty() {
+    let VoronoiRenderInputs {
+        render_voronoi_instances,
+        render_mesh_instances,
+        point_seeds,
+        voronoi_sprites,
+        voronoi_layers,
+    } = inputs;
+
+    if render_voronoi_instances.is_empty()
+        && point_seeds.seeds.is_empty()
+        && voronoi_sprites.sprites.is_empty()
+    {
         return;
     }
 
     // Pre-filter valid view entities to avoid repeated containment checks
     let mut valid_view_entities = HashSet::new();
-    for (entity, _, _) in views
+    for (view, ..) in views
         .iter()
-        .filter(|(_, view, _)| mask_render_phases.contains_key(&view.retained_view_entity))
+        .filter(|(view, ..)| mask_render_phases.contains_key(&view.retained_view_entity))
     {
-        valid_view_entities.insert(*entity);
+        valid_view_entities.insert(view.retained_view_entity);
     }
 
     // Retain only entries whose entities exist in the filtered views
     view_entities_render_cache.retain(|entity, _| valid_view_entities.contains(entity));
 
-    for (view_entity, view, visible_entities) in &views {
+    for (view, visible_entities, voronoi_camera, readback_image) in &views {
+        let view_entity = &view.retained_view_entity;
         if !valid_view_entities.contains(view_entity) {
             continue;
         }
 
+        let target_size = voronoi_camera
+            .target
+            .as_ref()
+            .and_then(|target| images.get(target).map(|image| image.size));
+        let readback_size = readback_image
+            .and_then(|readback_image| images.get(&readback_image.0).map(|image| image.size));
+
+        let sprites: Vec<_> = voronoi_sprites
+            .view_sprites(voronoi_camera)
+            .cloned()
+            .collect();
+
         let mut render_state = ViewEntityRenderState {
             camera_viewport: view.viewport,
             camera_transform: view.world_from_view,
+            voronoi_camera: voronoi_camera.clone(),
+            target_size,
+            readback_size,
             entity_transforms: EntityHashMap::new(),
-            material_assets: EntityHashMap::new(),
-            has_changed: false,
+            materials: EntityHashMap::new(),
+            point_seeds: point_seeds.view_seeds(voronoi_camera).collect(),
+            clip_from_world: view.clip_from_world.unwrap_or_else(|| {
+                view.clip_from_view * view.world_from_view.compute_matrix().inverse()
+            }),
+            texture_size: voronoi_camera.texture_size(view.viewport.zw()),
+            // Keep recomputing until the target, readback and sprite images are available
+            // on the GPU
+            has_changed: (voronoi_camera.target.is_some() && target_size.is_none())
+                || (readback_image.is_some() && readback_size.is_none())
+                || sprites
+                    .iter()
+                    .any(|sprite| images.get(sprite.image).is_none()),
+            sprites,
+            dirty_rect: None,
         };
 
         for (entity, visible_entity) in visible_entities.iter::<Mesh2d>() {
+            if !voronoi_camera.includes(voronoi_layers.get(*entity).ok()) {
+                continue;
+            }
+
             let Some(mesh_instance) = render_mesh_instances.get(visible_entity) else {
                 continue;
             };
@@ -384,11 +1365,11 @@ fn prepare_view_entities_render_cache(
                 },
             );
 
-            let Some(alpha_mask) = render_voronoi_instances.get(visible_entity) else {
+            let Some(material) = render_voronoi_instances.get(visible_entity) else {
                 continue;
             };
 
-            render_state.material_assets.insert(*entity, *alpha_mask);
+            render_state.materials.insert(*entity, *material);
         }
 
         // Update the cache with the new state for this view
@@ -396,23 +1377,57 @@ fn prepare_view_entities_render_cache(
     }
 }
 
+/// Mesh assets and instances of the mask pass.
+#[derive(SystemParam)]
+struct MaskMeshes<'w> {
+    render_meshes: Res<'w, RenderAssets<RenderMesh>>,
+    render_mesh_instances: ResMut<'w, RenderMesh2dInstances>,
+}
+
+/// View keys and change ticks deciding which mask meshes need to be specialized again.
+#[derive(SystemParam)]
+struct MaskSpecializationTicks<'w> {
+    view_key_cache: Res<'w, ViewKeyCache>,
+    view_specialization_ticks: Res<'w, ViewSpecializationTicks>,
+    entity_specialization_ticks: Res<'w, EntitySpecializationTicks<VoronoiMaterial>>,
+    ticks: SystemChangeTick,
+}
+
+/// The mask pipeline with the cache of its specializations.
+#[derive(SystemParam)]
+struct MaskPipelineSpecializers<'w> {
+    pipeline_cache: Res<'w, PipelineCache>,
+    mask_pipeline: Res<'w, MaskPipeline>,
+    mask_pipelines: ResMut<'w, SpecializedMeshPipelines<MaskPipeline>>,
+}
+
 fn specialize_mask_meshes(
     render_voronoi_instances: Res<RenderVoronoiMaterials>,
     views: Query<(&MainEntity, &ExtractedView, &RenderVisibleEntities)>,
     mask_render_phases: ResMut<ViewBinnedRenderPhases<MaskPhase>>,
-    view_key_cache: Res<ViewKeyCache>,
-    view_specialization_ticks: Res<ViewSpecializationTicks>,
+    specialization_ticks: MaskSpecializationTicks,
     mut specialized_material_pipeline_cache: ResMut<
         SpecializedMaterial2dPipelineCache<VoronoiMaterial>,
     >,
-    mut render_mesh_instances: ResMut<RenderMesh2dInstances>,
-    entity_specialization_ticks: Res<EntitySpecializationTicks<VoronoiMaterial>>,
-    ticks: SystemChangeTick,
-    render_meshes: Res<RenderAssets<RenderMesh>>,
-    pipeline_cache: Res<PipelineCache>,
-    mask_pipeline: Res<MaskPipeline>,
-    mut mask_pipelines: ResMut<SpecializedMeshPipelines<MaskPipeline>>,
+    meshes: MaskMeshes,
+    specializers: MaskPipelineSpecializers,
 ) {
+    let MaskSpecializationTicks {
+        view_key_cache,
+        view_specialization_ticks,
+        entity_specialization_ticks,
+        ticks,
+    } = specialization_ticks;
+    let MaskMeshes {
+        render_meshes,
+        mut render_mesh_instances,
+    } = meshes;
+    let MaskPipelineSpecializers {
+        pipeline_cache,
+        mask_pipeline,
+        mut mask_pipelines,
+    } = specializers;
+
     if render_voronoi_instances.is_empty() {
         return;
     }
@@ -480,10 +1495,15 @@ fn specialize_mask_meshes(
 
 fn queue_mask_meshes(
     flood_draw_functions: Res<DrawFunctions<MaskPhase>>,
-    render_meshes: Res<RenderAssets<RenderMesh>>,
-    mut render_mesh_instances: ResMut<RenderMesh2dInstances>,
+    meshes: MaskMeshes,
     mut mask_render_phase: ResMut<ViewBinnedRenderPhases<MaskPhase>>,
-    views: Query<(&MainEntity, &ExtractedView, &RenderVisibleEntities)>,
+    views: Query<(
+        &MainEntity,
+        &ExtractedView,
+        &RenderVisibleEntities,
+        &VoronoiCamera,
+    )>,
+    voronoi_layers: Query<&VoronoiLayers>,
     specialized_material_pipeline_cache: ResMut<
         SpecializedMaterial2dPipelineCache<VoronoiMaterial>,
     >,
@@ -493,7 +1513,12 @@ fn queue_mask_meshes(
         return;
     }
 
-    for (view_entity, view, visible_entities) in &views {
+    let MaskMeshes {
+        render_meshes,
+        mut render_mesh_instances,
+    } = meshes;
+
+    for (view_entity, view, visible_entities, voronoi_camera) in &views {
         let Some(view_specialized_material_pipeline_cache) =
             specialized_material_pipeline_cache.get(view_entity)
         else {
@@ -507,6 +1532,10 @@ fn queue_mask_meshes(
         let draw_flood_mesh = flood_draw_functions.read().id::<DrawMaskMesh>();
 
         for (render_entity, visible_entity) in visible_entities.iter::<Mesh2d>() {
+            if !voronoi_camera.includes(voronoi_layers.get(*render_entity).ok()) {
+                continue;
+            }
+
             let Some((current_change_tick, pipeline_id)) = view_specialized_material_pipeline_cache
                 .get(visible_entity)
                 .map(|(current_change_tick, pipeline_id)| (*current_change_tick, *pipeline_id))
@@ -546,11 +1575,49 @@ fn queue_mask_meshes(
     }
 }
 
+/// Ping-pong textures of the Voronoi diagram, covering the camera viewport.
+///
+/// The `Rgba16Float` seed textures hold the nearest seed texel coordinate in `xy`,
+/// relative to the viewport,
+/// `1.0` in `z` for original seeds, `-1.0` in `z` when no seed lies within
+/// [`VoronoiCamera::max_distance`] and the alpha mask in `w`, see [`SeedMode::is_inside`]
+/// for the pixels being inside. The `Rgba32Uint` object
+/// textures hold the [`VoronoiMaterial::id`] owning that seed in `x` and its
+/// [`VoronoiWeight`] as `f32` bits in `yz`.
+///
+/// With [`VoronoiCamera::second_seed`], the `Rgba32Uint` second seed textures hold the
+/// nearest seed of another object than the nearest seed, its texel coordinate packed as
+/// two `f16` in `x`, `-1.0` when there is none, and the `xyz` of its object texel in
+/// `yzw`. The cell border lies halfway between both seeds.
+///
+/// [`VoronoiTexture::result`] and [`VoronoiTexture::object_result`] hold the finished
+/// field, including on frames where it isn't recomputed. `input` and `output` are the
+/// ping-pong state of the passes and should not be relied upon after the flood node.
+/// With [`VoronoiCamera::flood_passes_per_frame`] the finished field is copied into
+/// separate result textures, the ping-pong textures holding the flood in progress, and
+/// the results are `None` until the first flood completes.
 #[derive(Clone, Component)]
 pub struct VoronoiTexture {
     flip: bool,
+    result_flip: bool,
+    /// Whether the textures are the ones the view used on the previous frame, a partial
+    /// recomputation needs their previous content.
+    reused: bool,
+    /// Region redrawn by the passes, the whole texture when `None`.
+    scissor: Option<URect>,
     texture_a: CachedTexture,
     texture_b: CachedTexture,
+    object_texture_a: CachedTexture,
+    object_texture_b: CachedTexture,
+    /// Second seed textures, with [`VoronoiCamera::second_seed`].
+    second_texture_a: Option<CachedTexture>,
+    second_texture_b: Option<CachedTexture>,
+    /// Seed, object and second seed textures the finished field is published to, when
+    /// the flood spans several frames.
+    published: Option<(CachedTexture, CachedTexture, Option<CachedTexture>)>,
+    /// Whether a flood completed into the published textures, by the end of the flood
+    /// node on the current frame.
+    has_published: bool,
 }
 
 impl VoronoiTexture {
@@ -570,23 +1637,216 @@ impl VoronoiTexture {
         }
     }
 
+    pub fn object_input(&self) -> &CachedTexture {
+        if self.flip {
+            &self.object_texture_b
+        } else {
+            &self.object_texture_a
+        }
+    }
+
+    pub fn object_output(&self) -> &CachedTexture {
+        if self.flip {
+            &self.object_texture_a
+        } else {
+            &self.object_texture_b
+        }
+    }
+
+    pub fn second_input(&self) -> Option<&CachedTexture> {
+        if self.flip {
+            self.second_texture_b.as_ref()
+        } else {
+            self.second_texture_a.as_ref()
+        }
+    }
+
+    pub fn second_output(&self) -> Option<&CachedTexture> {
+        if self.flip {
+            self.second_texture_a.as_ref()
+        } else {
+            self.second_texture_b.as_ref()
+        }
+    }
+
+    /// Seed texture holding the final flood output.
+    pub fn result(&self) -> Option<&CachedTexture> {
+        match &self.published {
+            Some(_) if !self.has_published => None,
+            Some((texture, _, _)) => Some(texture),
+            None => Some(self.flood_output()),
+        }
+    }
+
+    /// Object texture holding the final flood output.
+    pub fn object_result(&self) -> Option<&CachedTexture> {
+        match &self.published {
+            Some(_) if !self.has_published => None,
+            Some((_, object_texture, _)) => Some(object_texture),
+            None => Some(self.object_flood_output()),
+        }
+    }
+
+    /// Second seed texture holding the final flood output, with
+    /// [`VoronoiCamera::second_seed`].
+    pub fn second_result(&self) -> Option<&CachedTexture> {
+        match &self.published {
+            Some(_) if !self.has_published => None,
+            Some((_, _, second_texture)) => second_texture.as_ref(),
+            None => self.second_flood_output(),
+        }
+    }
+
+    /// Ping-pong seed texture the last flood pass writes into.
+    fn flood_output(&self) -> &CachedTexture {
+        if self.result_flip {
+            &self.texture_b
+        } else {
+            &self.texture_a
+        }
+    }
+
+    fn object_flood_output(&self) -> &CachedTexture {
+        if self.result_flip {
+            &self.object_texture_b
+        } else {
+            &self.object_texture_a
+        }
+    }
+
+    fn second_flood_output(&self) -> Option<&CachedTexture> {
+        if self.result_flip {
+            self.second_texture_b.as_ref()
+        } else {
+            self.second_texture_a.as_ref()
+        }
+    }
+
     pub fn flip(&mut self) {
         self.flip = !self.flip;
     }
+
+    pub fn scissor(&self) -> Option<URect> {
+        self.scissor
+    }
+
+    /// Attachment operations of a pass writing into `output`, keeping the texels outside
+    /// of the scissor rect.
+    pub fn output_operations<V: Default>(&self) -> Operations<V> {
+        Operations {
+            load: match self.scissor {
+                Some(_) => LoadOp::Load,
+                None => LoadOp::Clear(V::default()),
+            },
+            store: StoreOp::Store,
+        }
+    }
+
+    pub fn set_scissor(&self, pass: &mut TrackedRenderPass) {
+        if let Some(rect) = self.scissor {
+            pass.set_scissor_rect(rect.min.x, rect.min.y, rect.width(), rect.height());
+        }
+    }
+
+    /// Copies the scissor rect of the flood output into the other ping-pong textures, so
+    /// that both hold the finished field for the next partial recomputation.
+    fn copy_result_to_output(&self, render_context: &mut RenderContext) {
+        let (output, object_output, second_output) = if self.result_flip {
+            (
+                &self.texture_a,
+                &self.object_texture_a,
+                self.second_texture_a.as_ref(),
+            )
+        } else {
+            (
+                &self.texture_b,
+                &self.object_texture_b,
+                self.second_texture_b.as_ref(),
+            )
+        };
+        self.copy_flood_output(render_context, output, object_output, second_output);
+    }
+
+    /// Copies the flood output into the published result textures.
+    fn publish(&self, render_context: &mut RenderContext) {
+        if let Some((texture, object_texture, second_texture)) = &self.published {
+            self.copy_flood_output(
+                render_context,
+                texture,
+                object_texture,
+                second_texture.as_ref(),
+            );
+        }
+    }
+
+    fn copy_flood_output(
+        &self,
+        render_context: &mut RenderContext,
+        texture: &CachedTexture,
+        object_texture: &CachedTexture,
+        second_texture: Option<&CachedTexture>,
+    ) {
+        let size = self.flood_output().texture.size();
+        let rect = self
+            .scissor
+            .unwrap_or(URect::new(0, 0, size.width, size.height));
+
+        let second = self.second_flood_output().zip(second_texture);
+        for (source, destination) in [
+            (self.flood_output(), texture),
+            (self.object_flood_output(), object_texture),
+        ]
+        .into_iter()
+        .chain(second)
+        {
+            let origin = Origin3d {
+                x: rect.min.x,
+                y: rect.min.y,
+                z: 0,
+            };
+            render_context.command_encoder().copy_texture_to_texture(
+                TexelCopyTextureInfo {
+                    origin,
+                    ..source.texture.as_image_copy()
+                },
+                TexelCopyTextureInfo {
+                    origin,
+                    ..destination.texture.as_image_copy()
+                },
+                Extent3d {
+                    width: rect.width(),
+                    height: rect.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
 }
 
 fn create_aux_texture(
-    view_target: &ViewTarget,
+    view_size: UVec2,
     texture_cache: &mut TextureCache,
     render_device: &RenderDevice,
     label: &'static str,
-    scale: f32,
+    format: TextureFormat,
+    voronoi_camera: &VoronoiCamera,
+    storage: bool,
 ) -> CachedTexture {
-    let size = view_target.main_texture().size();
+    let mut usage = TextureUsages::RENDER_ATTACHMENT
+        | TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST;
+    // Only the ping-pong textures are bound by the compute flood passes, other formats
+    // may not support storage
+    if storage && voronoi_camera.flood_mode == FloodMode::Compute {
+        usage |= TextureUsages::STORAGE_BINDING;
+    }
+
+    let size = voronoi_camera.texture_size(view_size);
     let size = Extent3d {
-        width: (size.width as f32 * scale) as u32,
-        height: (size.height as f32 * scale) as u32,
-        depth_or_array_layers: size.depth_or_array_layers,
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
     };
 
     texture_cache.get(
@@ -597,130 +1857,695 @@ fn create_aux_texture(
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba16Float,
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            format,
+            usage,
             view_formats: &[],
         },
     )
 }
 
+/// Flood passes [`FloodDrawNode`] runs for a view this frame, see
+/// [`VoronoiCamera::flood_passes_per_frame`].
+#[derive(Clone, Component, Default)]
+pub struct VoronoiFloodProgress {
+    /// Whether the mask and seed passes run, starting a new flood.
+    reseed: bool,
+    /// Indices of the flood passes to run in [`VoronoiCamera::flood_passes`].
+    passes: Range<usize>,
+    /// Set on frames where a flood completes, the result textures, the
+    /// [`VoronoiDistanceTexture`] and the [`VoronoiCamera::target`] receiving a new field.
+    pub fresh: bool,
+    /// Projection of the view when the flood in progress was seeded.
+    clip_from_world: Mat4,
+}
+
+/// State of a view carried over to the next frame by [`prepare_flood_textures`].
+#[derive(Default)]
+struct ViewFloodState {
+    texture_ids: Vec<TextureId>,
+    voronoi_camera: Option<VoronoiCamera>,
+    /// First flood pass of the next frame while a flood is in progress.
+    next_pass: Option<usize>,
+    /// Set when the view changed during the flood in progress, starting a new one once it
+    /// completes.
+    restart_pending: bool,
+    has_published: bool,
+    clip_from_world: Mat4,
+}
+
+impl ViewFloodState {
+    /// Flood passes of the view this frame, given the state of the previous frame, along
+    /// with the state carried over to the next frame. Changes of the entities wait for the
+    /// flood in progress to complete so that fields changing every frame are still
+    /// published, new textures or settings restart it right away.
+    fn advance(
+        &self,
+        texture_ids: Vec<TextureId>,
+        voronoi_camera: &VoronoiCamera,
+        entities_changed: bool,
+        pass_count: usize,
+        clip_from_world: Mat4,
+    ) -> (VoronoiFloodProgress, ViewFloodState) {
+        let reused = self.texture_ids == texture_ids;
+        let changed = self.restart_pending || entities_changed;
+        let restart = !reused
+            || self.voronoi_camera.as_ref() != Some(voronoi_camera)
+            || (changed && self.next_pass.is_none());
+        let start = if restart { Some(0) } else { self.next_pass };
+        let clip_from_world = if restart {
+            clip_from_world
+        } else {
+            self.clip_from_world
+        };
+
+        let progress = match start {
+            Some(start) => {
+                let end = voronoi_camera
+                    .flood_passes_per_frame
+                    .map_or(pass_count, |passes| {
+                        (start + passes.max(1) as usize).min(pass_count)
+                    });
+                VoronoiFloodProgress {
+                    reseed: restart,
+                    passes: start..end,
+                    fresh: end == pass_count,
+                    clip_from_world,
+                }
+            }
+            None => VoronoiFloodProgress::default(),
+        };
+
+        let state = ViewFloodState {
+            texture_ids,
+            voronoi_camera: Some(voronoi_camera.clone()),
+            next_pass: start
+                .map(|_| progress.passes.end)
+                .filter(|end| *end < pass_count),
+            restart_pending: changed && !restart,
+            has_published: progress.fresh || (reused && self.has_published),
+            clip_from_world,
+        };
+
+        (progress, state)
+    }
+}
+
 fn prepare_flood_textures(
     mut commands: Commands,
-    view_query: Query<(Entity, &ViewTarget, &ExtractedView, &VoronoiCamera)>,
+    view_query: Query<(Entity, &ExtractedView, &VoronoiCamera)>,
     flood_mask_phases: Res<ViewBinnedRenderPhases<MaskPhase>>,
+    view_entities_render_cache: Res<ViewEntitiesRenderCache>,
     render_device: Res<RenderDevice>,
     mut texture_cache: ResMut<TextureCache>,
+    mut previous_states: Local<EntityHashMap<ViewFloodState>>,
 ) {
-    for (entity, view_target, extracted_view, voronoi_camera) in &view_query {
+    let mut current_states = EntityHashMap::default();
+
+    for (entity, extracted_view, voronoi_camera) in &view_query {
         if !flood_mask_phases.contains_key(&extracted_view.retained_view_entity) {
             continue;
         }
 
-        commands.entity(entity).insert(VoronoiTexture {
-            flip: false,
-            texture_a: create_aux_texture(
-                view_target,
+        let mut create_texture = |label, format, storage| {
+            // Sized and addressed relative to the camera viewport
+            create_aux_texture(
+                extracted_view.viewport.zw(),
                 &mut texture_cache,
                 &render_device,
-                "flood_texture_a",
-                voronoi_camera.scale,
-            ),
-            texture_b: create_aux_texture(
-                view_target,
-                &mut texture_cache,
-                &render_device,
-                "flood_texture_b",
-                voronoi_camera.scale,
-            ),
+                label,
+                format,
+                voronoi_camera,
+                storage,
+            )
+        };
+
+        // The mask and seed passes flip twice and every flood pass once, in both flood
+        // modes, so the result only moves when the size or the settings change, which
+        // always triggers a recomputation
+        let size = voronoi_camera.texture_size(extracted_view.viewport.zw());
+        let pass_count = voronoi_camera.flood_passes(size).len();
+        let result_flip = pass_count % 2 == 1;
+
+        let texture_a = create_texture("flood_texture_a", TextureFormat::Rgba16Float, true);
+        let texture_b = create_texture("flood_texture_b", TextureFormat::Rgba16Float, true);
+        let object_texture_a =
+            create_texture("flood_object_texture_a", TextureFormat::Rgba32Uint, true);
+        let object_texture_b =
+            create_texture("flood_object_texture_b", TextureFormat::Rgba32Uint, true);
+        let second_seed = voronoi_camera.second_seed;
+        let second_texture_a = second_seed
+            .then(|| create_texture("flood_second_texture_a", TextureFormat::Rgba32Uint, false));
+        let second_texture_b = second_seed
+            .then(|| create_texture("flood_second_texture_b", TextureFormat::Rgba32Uint, false));
+        let published = voronoi_camera.flood_passes_per_frame.map(|_| {
+            (
+                create_texture("flood_result_texture", TextureFormat::Rgba16Float, false),
+                create_texture(
+                    "flood_object_result_texture",
+                    TextureFormat::Rgba32Uint,
+                    false,
+                ),
+                second_seed.then(|| {
+                    create_texture(
+                        "flood_second_result_texture",
+                        TextureFormat::Rgba32Uint,
+                        false,
+                    )
+                }),
+            )
         });
+
+        // The texture cache doesn't guarantee a view gets the same textures every frame
+        let mut texture_ids = vec![
+            texture_a.texture.id(),
+            texture_b.texture.id(),
+            object_texture_a.texture.id(),
+            object_texture_b.texture.id(),
+        ];
+        if let Some((texture, object_texture, _)) = &published {
+            texture_ids.extend([texture.texture.id(), object_texture.texture.id()]);
+        }
+        let second_textures = [&second_texture_a, &second_texture_b].into_iter().chain(
+            published
+                .as_ref()
+                .map(|(_, _, second_texture)| second_texture),
+        );
+        texture_ids.extend(
+            second_textures
+                .flatten()
+                .map(|texture| texture.texture.id()),
+        );
+        // The distance pass only runs when a flood completes, so a new distance texture
+        // needs a new flood as well
+        let distance_texture = voronoi_camera
+            .distance_field
+            .then(|| create_texture("flood_distance_texture", TextureFormat::R16Float, false));
+        texture_ids.extend(
+            distance_texture
+                .as_ref()
+                .map(|texture| texture.texture.id()),
+        );
+        let previous_state = previous_states.remove(&entity).unwrap_or_default();
+        let reused = previous_state.texture_ids == texture_ids;
+        let entities_changed = view_entities_render_cache
+            .get(&extracted_view.retained_view_entity)
+            .is_none_or(|render_cache_state| render_cache_state.has_changed);
+        let clip_from_world = extracted_view.clip_from_world.unwrap_or_else(|| {
+            extracted_view.clip_from_view
+                * extracted_view.world_from_view.compute_matrix().inverse()
+        });
+
+        let (progress, state) = previous_state.advance(
+            texture_ids,
+            voronoi_camera,
+            entities_changed,
+            pass_count,
+            clip_from_world,
+        );
+        let has_published = state.has_published;
+        current_states.insert(entity, state);
+
+        commands.entity(entity).insert((
+            VoronoiTexture {
+                // Flipped twice by the mask and seed passes and once by every flood pass
+                flip: progress.passes.start % 2 == 1,
+                result_flip,
+                reused,
+                scissor: None,
+                texture_a,
+                texture_b,
+                object_texture_a,
+                object_texture_b,
+                second_texture_a,
+                second_texture_b,
+                published,
+                has_published,
+            },
+            progress,
+        ));
+
+        if let Some(texture) = distance_texture {
+            commands
+                .entity(entity)
+                .insert(VoronoiDistanceTexture { texture });
+        } else {
+            commands.entity(entity).remove::<VoronoiDistanceTexture>();
+        }
     }
+
+    *previous_states = current_states;
 }
 
 #[derive(RenderLabel, Debug, Clone, Hash, PartialEq, Eq)]
 struct FloodDrawPassLabel;
 
-#[derive(Default)]
-struct FloodDrawNode;
+type FloodViewQuery = (
+    Read<ExtractedView>,
+    Read<VoronoiTexture>,
+    Read<VoronoiCamera>,
+    Read<ViewFloodPipelines>,
+    Read<VoronoiFloodProgress>,
+    Option<Read<VoronoiDistanceTexture>>,
+    Option<Read<VoronoiReadbackImage>>,
+);
+
+/// Runs the passes of the camera view, then of the views of its [`VoronoiChannels`].
+struct FloodDrawNode {
+    channel_views: QueryState<FloodViewQuery>,
+}
+
+impl FromWorld for FloodDrawNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            channel_views: QueryState::new(world),
+        }
+    }
+}
+
 impl ViewNode for FloodDrawNode {
-    type ViewQuery = (
-        Read<MainEntity>,
-        Read<ExtractedCamera>,
-        Read<ExtractedView>,
-        Read<ViewTarget>,
-        Read<VoronoiTexture>,
-    );
+    type ViewQuery = (FloodViewQuery, Option<Read<VoronoiChannelViews>>);
+
+    fn update(&mut self, world: &mut World) {
+        self.channel_views.update_archetypes(world);
+    }
 
     fn run<'w>(
         &self,
         graph: &mut RenderGraphContext,
         render_context: &mut RenderContext<'w>,
-        (main_entity, camera, view, target, voronoi_textures): QueryItem<'w, Self::ViewQuery>,
+        (view, channel_views): QueryItem<'w, Self::ViewQuery>,
         world: &'w World,
     ) -> Result<(), NodeRunError> {
-        let view_entity = graph.view_entity();
+        Self::run_view(world, render_context, graph.view_entity(), view)?;
 
-        if let Some(render_cache_state) =
-            world.resource::<ViewEntitiesRenderCache>().get(main_entity)
+        for (_, channel_view) in channel_views
+            .into_iter()
+            .flat_map(VoronoiChannelViews::iter)
         {
-            if !render_cache_state.has_changed {
-                return Ok(());
+            if let Ok(view) = self.channel_views.get_manual(world, channel_view) {
+                Self::run_view(world, render_context, channel_view, view)?;
             }
         }
 
-        let mut voronoi_textures = voronoi_textures.clone();
+        Ok(())
+    }
+}
 
-        run_mask_pass(
-            world,
-            render_context,
-            &view.retained_view_entity,
-            &view_entity,
-            voronoi_textures.output(),
-            camera,
-        );
-        voronoi_textures.flip();
-
-        run_flood_seed_pass(
-            world,
-            render_context,
-            camera,
-            voronoi_textures.input(),
-            voronoi_textures.output(),
-        );
-        voronoi_textures.flip();
+impl FloodDrawNode {
+    fn run_view<'w>(
+        world: &'w World,
+        render_context: &mut RenderContext<'w>,
+        view_entity: Entity,
+        (
+            view,
+            voronoi_textures,
+            voronoi_camera,
+            flood_pipelines,
+            progress,
+            distance_texture,
+            readback_image,
+        ): QueryItem<'w, FloodViewQuery>,
+    ) -> Result<(), NodeRunError> {
+        if !progress.reseed && progress.passes.is_empty() {
+            return Ok(());
+        }
+
+        let render_cache_state = world
+            .resource::<ViewEntitiesRenderCache>()
+            .get(&view.retained_view_entity);
 
-        let width = target.main_texture().width();
-        let height = target.main_texture().height();
-        let max_dim = width.max(height);
-        let mut step = max_dim / 2;
+        let compute_pipeline = flood_pipelines
+            .compute_pipeline
+            .filter(|_| voronoi_camera.flood_mode == FloodMode::Compute);
 
-        while step >= 1 {
-            let x_step = (step * width) / max_dim;
-            let y_step = (step * height) / max_dim;
+        // Partial recomputations rely on both ping-pong textures holding the finished
+        // field outside of the scissor rect, compute passes always cover the whole texture
+        let partial = voronoi_camera.max_distance.is_some()
+            && voronoi_camera.flood_passes_per_frame.is_none()
+            && compute_pipeline.is_none();
+
+        let mut voronoi_textures = voronoi_textures.clone();
+        voronoi_textures.scissor = render_cache_state
+            .and_then(|render_cache_state| render_cache_state.dirty_rect)
+            .filter(|_| {
+                partial
+                    && voronoi_textures.reused
+                    && world
+                        .resource::<PipelineCache>()
+                        .get_render_pipeline(world.resource::<MaskClearPipeline>().pipeline)
+                        .is_some()
+            });
 
-            run_flood_pass(
+        if progress.reseed {
+            run_mask_pass(
                 world,
                 render_context,
-                camera,
-                voronoi_textures.input(),
-                voronoi_textures.output(),
-                UVec2::new(x_step.max(1), y_step.max(1)),
+                &view.retained_view_entity,
+                &view_entity,
+                &voronoi_textures,
             );
+            run_sprite_mask_pass(world, render_context, view_entity, view, &voronoi_textures);
+            voronoi_textures.flip();
 
+            run_flood_seed_pass(
+                world,
+                render_context,
+                &voronoi_textures,
+                flood_pipelines.seed_pipeline,
+            );
+            run_point_seed_pass(world, render_context, view_entity, view, &voronoi_textures);
             voronoi_textures.flip();
-            step /= 2;
         }
 
-        // Addicional pass with step = 1 to improve accuracy
-        run_flood_pass(
-            world,
-            render_context,
-            camera,
-            voronoi_textures.input(),
-            voronoi_textures.output(),
-            UVec2::new(1, 1),
+        let size = voronoi_textures.input().texture.size();
+        let passes = voronoi_camera.flood_passes(UVec2::new(size.width, size.height));
+        let passes = &passes[progress.passes.clone()];
+
+        if let Some(compute_pipeline) = compute_pipeline {
+            run_flood_compute_passes(
+                world,
+                render_context,
+                &mut voronoi_textures,
+                compute_pipeline,
+                passes,
+            );
+        } else {
+            for settings in passes {
+                run_flood_pass(
+                    world,
+                    render_context,
+                    &voronoi_textures,
+                    flood_pipelines.pipeline,
+                    *settings,
+                );
+                voronoi_textures.flip();
+            }
+        }
+
+        // The flood continues on the next frames
+        if !progress.fresh {
+            return Ok(());
+        }
+
+        debug_assert_eq!(
+            voronoi_textures.input().texture.id(),
+            voronoi_textures.flood_output().texture.id(),
+            "the flood output doesn't match the parity computed in prepare_flood_textures",
         );
-        voronoi_textures.flip();
+
+        if partial {
+            voronoi_textures.copy_result_to_output(render_context);
+        }
+        voronoi_textures.publish(render_context);
+
+        if let (Some(distance_texture), Some(distance_pipeline)) =
+            (distance_texture, flood_pipelines.distance_pipeline)
+        {
+            let size = voronoi_textures.input().texture.size();
+            run_distance_pass(
+                world,
+                render_context,
+                &voronoi_textures,
+                distance_texture,
+                distance_pipeline,
+                // Like the readback, in the projection the flood was seeded with
+                DistanceUniform::new(
+                    progress.clip_from_world,
+                    voronoi_camera,
+                    UVec2::new(size.width, size.height),
+                ),
+            );
+        }
+
+        let Some(result) = voronoi_textures.result() else {
+            return Ok(());
+        };
+
+        if let Some(target) = voronoi_camera.target.as_ref() {
+            copy_to_target(world, render_context, result, target);
+        }
+
+        if let Some(readback_image) = readback_image {
+            copy_to_readback(
+                world,
+                render_context,
+                progress.clip_from_world,
+                result,
+                readback_image,
+            );
+        }
 
         Ok(())
     }
 }
+
+fn copy_to_target(
+    world: &World,
+    render_context: &mut RenderContext,
+    input: &CachedTexture,
+    target: &Handle<Image>,
+) {
+    let Some(target) = world.resource::<RenderAssets<GpuImage>>().get(target) else {
+        return;
+    };
+
+    if target.texture_format != TextureFormat::Rgba16Float {
+        return;
+    }
+
+    let size = input.texture.size();
+    let size = Extent3d {
+        width: size.width.min(target.size.width),
+        height: size.height.min(target.size.height),
+        depth_or_array_layers: 1,
+    };
+
+    render_context.command_encoder().copy_texture_to_texture(
+        input.texture.as_image_copy(),
+        target.texture.as_image_copy(),
+        size,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_uniform_packs_the_mask_settings() {
+        let uniform = VoronoiMaterial::default().uniform(3);
+        assert_eq!(uniform.id, 3);
+        assert_eq!(uniform.channel, MaskChannel::A as u32);
+        assert_eq!(uniform.flags, 0);
+
+        let uniform = VoronoiMaterial::default()
+            .with_id(5)
+            .with_channel(MaskChannel::Luminance)
+            .inverted()
+            .with_threshold(0.25)
+            .uniform(3);
+        assert_eq!(uniform.id, 5);
+        assert_eq!(uniform.channel, 4);
+        assert_eq!(uniform.flags, MASK_FLAG_INVERT | MASK_FLAG_THRESHOLD);
+        assert_eq!(uniform.threshold, 0.25);
+    }
+
+    #[test]
+    fn uv_rects_map_the_unit_square() {
+        let material = VoronoiMaterial::default().with_uv_rect(Rect::new(0.25, 0.5, 0.75, 1.0));
+        let uv = |uv| material.uv_transform.transform_point2(uv);
+        assert_eq!(uv(Vec2::ZERO), Vec2::new(0.25, 0.5));
+        assert_eq!(uv(Vec2::ONE), Vec2::new(0.75, 1.0));
+        assert_eq!(uv(Vec2::splat(0.5)), Vec2::new(0.5, 0.75));
+    }
+
+    #[test]
+    fn atlas_frames_select_their_rect() {
+        let layout = TextureAtlasLayout::from_grid(UVec2::splat(16), 4, 2, None, None);
+
+        // Frame 5 is the second of the bottom row of the 64x32 atlas
+        let material = VoronoiMaterial::default().with_atlas_frame(&layout, 5);
+        let uv = |uv| material.uv_transform.transform_point2(uv);
+        assert_eq!(uv(Vec2::ZERO), Vec2::new(0.25, 0.5));
+        assert_eq!(uv(Vec2::ONE), Vec2::new(0.5, 1.0));
+
+        let material = VoronoiMaterial::default().with_atlas_frame(&layout, 8);
+        assert_eq!(material.uv_transform, Affine2::IDENTITY);
+    }
+
+    const PASS_COUNT: usize = 5;
+
+    fn advance(
+        state: &ViewFloodState,
+        voronoi_camera: &VoronoiCamera,
+        entities_changed: bool,
+    ) -> (VoronoiFloodProgress, ViewFloodState) {
+        state.advance(
+            state.texture_ids.clone(),
+            voronoi_camera,
+            entities_changed,
+            PASS_COUNT,
+            Mat4::IDENTITY,
+        )
+    }
+
+    fn amortised_camera(passes: u32) -> VoronoiCamera {
+        VoronoiCamera {
+            flood_passes_per_frame: Some(passes),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn first_frame_runs_every_pass() {
+        let (progress, state) = advance(&ViewFloodState::default(), &default(), false);
+
+        assert!(progress.reseed);
+        assert_eq!(progress.passes, 0..PASS_COUNT);
+        assert!(progress.fresh);
+        assert!(state.has_published);
+        assert_eq!(state.next_pass, None);
+    }
+
+    #[test]
+    fn unchanged_views_skip_the_flood() {
+        let voronoi_camera = VoronoiCamera::default();
+        let (_, state) = advance(&ViewFloodState::default(), &voronoi_camera, false);
+        let (progress, state) = advance(&state, &voronoi_camera, false);
+
+        assert!(!progress.reseed);
+        assert!(progress.passes.is_empty());
+        assert!(state.has_published);
+    }
+
+    #[test]
+    fn amortised_floods_run_at_least_one_pass_per_frame() {
+        let voronoi_camera = amortised_camera(0);
+        let mut state = ViewFloodState::default();
+
+        for pass in 0..PASS_COUNT {
+            let (progress, next_state) = advance(&state, &voronoi_camera, false);
+            assert_eq!(progress.reseed, pass == 0);
+            assert_eq!(progress.passes, pass..pass + 1);
+            assert_eq!(progress.fresh, pass == PASS_COUNT - 1);
+            assert_eq!(next_state.has_published, pass == PASS_COUNT - 1);
+            state = next_state;
+        }
+    }
+
+    #[test]
+    fn entity_changes_wait_for_the_flood_in_progress() {
+        let voronoi_camera = amortised_camera(2);
+        let (_, state) = advance(&ViewFloodState::default(), &voronoi_camera, false);
+
+        let (progress, state) = advance(&state, &voronoi_camera, true);
+        assert!(!progress.reseed);
+        assert_eq!(progress.passes, 2..4);
+        assert!(state.restart_pending);
+
+        let (progress, state) = advance(&state, &voronoi_camera, false);
+        assert_eq!(progress.passes, 4..PASS_COUNT);
+        assert!(progress.fresh);
+        assert!(state.restart_pending);
+
+        let (progress, state) = advance(&state, &voronoi_camera, false);
+        assert!(progress.reseed);
+        assert_eq!(progress.passes, 0..2);
+        assert!(!state.restart_pending);
+        assert!(state.has_published);
+    }
+
+    #[test]
+    fn settings_changes_restart_the_flood_right_away() {
+        let (_, state) = advance(&ViewFloodState::default(), &amortised_camera(2), false);
+
+        let (progress, state) = advance(&state, &amortised_camera(3), false);
+        assert!(progress.reseed);
+        assert_eq!(progress.passes, 0..3);
+        assert!(!state.has_published);
+    }
+
+    #[test]
+    fn new_textures_restart_the_flood() {
+        let voronoi_camera = VoronoiCamera::default();
+        let (_, state) = ViewFloodState::default().advance(
+            vec![TextureId::new()],
+            &voronoi_camera,
+            false,
+            PASS_COUNT,
+            Mat4::IDENTITY,
+        );
+
+        // E.g. the texture cache handing out another distance texture
+        let (progress, state) = state.advance(
+            vec![TextureId::new()],
+            &voronoi_camera,
+            false,
+            PASS_COUNT,
+            Mat4::IDENTITY,
+        );
+        assert!(progress.reseed);
+        assert_eq!(progress.passes, 0..PASS_COUNT);
+        assert!(state.has_published);
+    }
+
+    #[test]
+    fn restarts_keep_the_projection_of_the_seeding_frame() {
+        let voronoi_camera = amortised_camera(2);
+        let seeded = Mat4::from_scale(Vec3::splat(2.0));
+        let (_, state) =
+            ViewFloodState::default().advance(vec![], &voronoi_camera, false, PASS_COUNT, seeded);
+
+        let (progress, _) = advance(&state, &voronoi_camera, false);
+        assert_eq!(progress.clip_from_world, seeded);
+    }
+
+    fn dirty_state(camera_translation: Vec2) -> ViewEntityRenderState {
+        ViewEntityRenderState {
+            texture_size: UVec2::splat(64),
+            clip_from_world: Mat4::orthographic_rh(-32.0, 32.0, -32.0, 32.0, -1.0, 1.0)
+                * Mat4::from_translation(-camera_translation.extend(0.0)),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn texel_rects_cover_the_inflated_bounds() {
+        let state = dirty_state(Vec2::ZERO);
+
+        // Texel y runs down while world y runs up
+        let rect = state.texel_rect(Rect::new(-8.0, -4.0, 8.0, 4.0), 2.0);
+        assert_eq!(rect, URect::new(22, 26, 42, 38));
+
+        // Partially covered texels are included
+        let rect = state.texel_rect(Rect::new(0.5, 0.5, 1.5, 1.5), 0.0);
+        assert_eq!(rect, URect::new(32, 30, 34, 32));
+    }
+
+    #[test]
+    fn texel_rects_clamp_to_the_texture() {
+        let state = dirty_state(Vec2::ZERO);
+
+        let rect = state.texel_rect(Rect::new(20.0, 20.0, 40.0, 40.0), 2.0);
+        assert_eq!(rect, URect::new(50, 0, 64, 14));
+
+        let rect = state.texel_rect(Rect::new(-40.0, -40.0, 40.0, 40.0), 2.0);
+        assert_eq!(rect, URect::new(0, 0, 64, 64));
+
+        let rect = state.texel_rect(Rect::new(40.0, 40.0, 50.0, 50.0), 2.0);
+        assert_eq!(rect, URect::default());
+    }
+
+    #[test]
+    fn texel_rects_follow_the_camera() {
+        let offset = Vec2::new(100.0, -50.0);
+        let state = dirty_state(offset);
+
+        let rect = state.texel_rect(Rect::from_center_size(offset, Vec2::new(16.0, 8.0)), 2.0);
+        assert_eq!(rect, URect::new(22, 26, 42, 38));
+
+        // Bounds that were on screen before the camera moved now lie off it
+        let rect = state.texel_rect(Rect::new(-8.0, -4.0, 8.0, 4.0), 2.0);
+        assert_eq!(rect, URect::default());
+    }
+}
@@ -0,0 +1,36 @@
+@group(0) @binding(0) var<uniform> clip_from_world: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) id: u32,
+    @location(2) weight: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+    @location(1) @interpolate(flat) weight: vec2<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) seed: vec4<f32>,
+    @location(1) object: vec4<u32>,
+}
+
+@vertex
+fn vertex(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = clip_from_world * vec4(in.position, 1.0);
+    out.id = in.id;
+    out.weight = in.weight;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    // The alpha channel is excluded from the write mask to keep the alpha mask
+    out.seed = vec4(in.position.xy, 1.0, 0.0);
+    out.object = vec4(in.id, bitcast<vec2<u32>>(in.weight), 0u);
+    return out;
+}
@@ -0,0 +1,348 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferInitDescriptor, BufferUsages, Extent3d, Origin3d, TexelCopyBufferInfo,
+            TexelCopyBufferLayout, TexelCopyTextureInfo, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::{CachedTexture, GpuImage},
+    },
+};
+use half::f16;
+
+use crate::{cpu::VoronoiField, plugin::VoronoiCamera};
+
+/// Opt-in marker that reads the Voronoi diagram of a `VoronoiCamera` back to the main
+/// world every frame and publishes it as a [`VoronoiReadbackField`] on the camera entity.
+#[derive(Component, Clone, Copy, Default)]
+#[require(VoronoiCamera)]
+pub struct VoronoiReadback;
+
+/// The last Voronoi diagram read back from the GPU, along with the camera projection
+/// it was rendered with.
+#[derive(Component, Clone)]
+pub struct VoronoiReadbackField {
+    field: VoronoiField,
+    clip_from_world: Mat4,
+    world_from_clip: Mat4,
+}
+impl VoronoiReadbackField {
+    pub fn field(&self) -> &VoronoiField {
+        &self.field
+    }
+
+    /// Fragment coordinate in the Voronoi texture for a world position.
+    pub fn world_to_texel(&self, world_pos: Vec2) -> Vec2 {
+        let ndc = self.clip_from_world.project_point3(world_pos.extend(0.0));
+        Vec2::new(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * self.field.size().as_vec2()
+    }
+
+    /// World position of a fragment coordinate in the Voronoi texture.
+    pub fn texel_to_world(&self, texel: Vec2) -> Vec2 {
+        let uv = texel / self.field.size().as_vec2();
+        let ndc = Vec3::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0);
+        self.world_from_clip.project_point3(ndc).truncate()
+    }
+
+    fn texel_at(&self, world_pos: Vec2) -> Option<UVec2> {
+        let texel = self.world_to_texel(world_pos).floor();
+        let size = self.field.size().as_vec2();
+        (texel.cmpge(Vec2::ZERO).all() && texel.cmplt(size).all()).then(|| texel.as_uvec2())
+    }
+
+    /// World position of the seed nearest to `world_pos`, if it lies inside the field.
+    pub fn nearest_seed(&self, world_pos: Vec2) -> Option<Vec2> {
+        let seed = self.field.nearest_seed(self.texel_at(world_pos)?)?;
+        Some(self.texel_to_world(seed))
+    }
+
+    /// Distance in world units from `world_pos` to the nearest seed in the camera's
+    /// [`DistanceMetric`](crate::prelude::DistanceMetric), negative inside the mask.
+    pub fn sample_distance(&self, world_pos: Vec2) -> Option<f32> {
+        let texel = self.texel_at(world_pos)?;
+        let seed = self.texel_to_world(self.field.nearest_seed(texel)?);
+        let dist = self.field.metric().distance(world_pos, seed);
+        Some(
+            if self.field.seed_mode().is_inside(self.field.get(texel).w) {
+                -dist
+            } else {
+                dist
+            },
+        )
+    }
+}
+
+/// Texels of the first row of the readback image holding the size of the field and the
+/// `clip_from_world` matrix it was rendered with, followed by the field itself.
+const HEADER_TEXELS: u32 = 9;
+
+const PIXEL_SIZE: usize = 8;
+
+/// Image the flood output of a [`VoronoiReadback`] camera is copied into along with its
+/// header, see [`HEADER_TEXELS`].
+#[derive(Component, ExtractComponent, Clone)]
+pub(crate) struct VoronoiReadbackImage(pub Handle<Image>);
+
+impl VoronoiReadbackImage {
+    /// Size of the readback image holding a field of `field_size` texels.
+    pub fn size(field_size: UVec2) -> UVec2 {
+        UVec2::new(field_size.x.max(HEADER_TEXELS), field_size.y + 1)
+    }
+}
+
+pub(crate) fn prepare_voronoi_readbacks(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<VoronoiReadback>, Without<VoronoiReadbackImage>)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for entity in &cameras {
+        let mut image = Image::new_fill(
+            Extent3d::default(),
+            TextureDimension::D2,
+            &[0; PIXEL_SIZE],
+            TextureFormat::Rgba16Float,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::COPY_DST | TextureUsages::COPY_SRC;
+        let image = images.add(image);
+
+        commands.entity(entity).insert((
+            VoronoiReadbackImage(image.clone()),
+            Readback::texture(image),
+        ));
+    }
+}
+
+pub(crate) fn resize_voronoi_readbacks(
+    cameras: Query<(&Camera, &VoronoiCamera, &VoronoiReadbackImage)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (camera, voronoi_camera, readback_image) in &cameras {
+        let Some(target_size) = camera.physical_viewport_size() else {
+            continue;
+        };
+
+        let size = VoronoiReadbackImage::size(voronoi_camera.texture_size(target_size));
+        let size = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+
+        if images
+            .get(&readback_image.0)
+            .is_none_or(|image| image.texture_descriptor.size == size)
+        {
+            continue;
+        }
+
+        if let Some(image) = images.get_mut(&readback_image.0) {
+            image.data = None;
+            image.texture_descriptor.size = size;
+        }
+    }
+}
+
+pub(crate) fn remove_voronoi_readback(
+    trigger: Trigger<OnRemove, VoronoiReadback>,
+    mut commands: Commands,
+) {
+    if let Ok(mut entity) = commands.get_entity(trigger.target()) {
+        entity.remove::<(Readback, VoronoiReadbackImage, VoronoiReadbackField)>();
+    }
+}
+
+/// Copies the flood output into its readback image, below a header holding its size and
+/// the `clip_from_world` projection the flood was seeded with.
+pub(crate) fn copy_to_readback(
+    world: &World,
+    render_context: &mut RenderContext,
+    clip_from_world: Mat4,
+    input: &CachedTexture,
+    readback_image: &VoronoiReadbackImage,
+) {
+    let Some(target) = world
+        .resource::<RenderAssets<GpuImage>>()
+        .get(&readback_image.0)
+    else {
+        return;
+    };
+
+    let size = input.texture.size();
+    let image_size = VoronoiReadbackImage::size(UVec2::new(size.width, size.height));
+    if target.texture_format != TextureFormat::Rgba16Float
+        || target.size.width != image_size.x
+        || target.size.height != image_size.y
+    {
+        return;
+    }
+
+    let mut header = Vec::with_capacity(HEADER_TEXELS as usize * PIXEL_SIZE);
+    header.extend(size.width.to_le_bytes());
+    header.extend(size.height.to_le_bytes());
+    for value in clip_from_world.to_cols_array() {
+        header.extend(value.to_le_bytes());
+    }
+
+    let header = render_context
+        .render_device()
+        .create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("voronoi_readback_header"),
+            contents: &header,
+            usage: BufferUsages::COPY_SRC,
+        });
+
+    let command_encoder = render_context.command_encoder();
+    command_encoder.copy_buffer_to_texture(
+        TexelCopyBufferInfo {
+            buffer: &header,
+            layout: TexelCopyBufferLayout::default(),
+        },
+        target.texture.as_image_copy(),
+        Extent3d {
+            width: HEADER_TEXELS,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    );
+    command_encoder.copy_texture_to_texture(
+        input.texture.as_image_copy(),
+        TexelCopyTextureInfo {
+            origin: Origin3d { x: 0, y: 1, z: 0 },
+            ..target.texture.as_image_copy()
+        },
+        size,
+    );
+}
+
+pub(crate) fn receive_voronoi_readback(
+    trigger: Trigger<ReadbackComplete>,
+    mut commands: Commands,
+    cameras: Query<&VoronoiCamera, With<VoronoiReadback>>,
+) {
+    let Ok(voronoi_camera) = cameras.get(trigger.target()) else {
+        return;
+    };
+
+    let Some((size, clip_from_world, texels)) = decode_readback(&trigger.event().0) else {
+        return;
+    };
+
+    commands
+        .entity(trigger.target())
+        .insert(VoronoiReadbackField {
+            field: VoronoiField::from_texels(size, texels)
+                .with_metric(voronoi_camera.metric)
+                .with_seed_mode(voronoi_camera.seed_mode),
+            clip_from_world,
+            world_from_clip: clip_from_world.inverse(),
+        });
+}
+
+/// Decodes the field size, projection and texels of a readback image, `None` until a
+/// field has been copied into it.
+fn decode_readback(bytes: &[u8]) -> Option<(UVec2, Mat4, Vec<Vec4>)> {
+    let word = |i: usize| -> Option<[u8; 4]> { bytes.get(i * 4..i * 4 + 4)?.try_into().ok() };
+
+    let size = UVec2::new(u32::from_le_bytes(word(0)?), u32::from_le_bytes(word(1)?));
+    if size.cmpeq(UVec2::ZERO).any() {
+        return None;
+    }
+
+    let mut columns = [0.0; 16];
+    for (i, value) in columns.iter_mut().enumerate() {
+        *value = f32::from_le_bytes(word(2 + i)?);
+    }
+
+    let image_size = VoronoiReadbackImage::size(size);
+    let row_size = size.x as usize * PIXEL_SIZE;
+    let padded_row_size =
+        RenderDevice::align_copy_bytes_per_row(image_size.x as usize * PIXEL_SIZE);
+    if bytes.len() < padded_row_size * size.y as usize + row_size {
+        return None;
+    }
+
+    let texels = (1..=size.y as usize)
+        .flat_map(|y| {
+            bytes[y * padded_row_size..y * padded_row_size + row_size].chunks_exact(PIXEL_SIZE)
+        })
+        .map(|pixel| {
+            let channel = |i: usize| {
+                f16::from_bits(u16::from_le_bytes([pixel[i * 2], pixel[i * 2 + 1]])).to_f32()
+            };
+            Vec4::new(channel(0), channel(1), channel(2), channel(3))
+        })
+        .collect();
+
+    Some((size, Mat4::from_cols_array(&columns), texels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_readback(size: UVec2, clip_from_world: Mat4, texels: &[Vec4]) -> Vec<u8> {
+        let image_size = VoronoiReadbackImage::size(size);
+        let padded_row_size =
+            RenderDevice::align_copy_bytes_per_row(image_size.x as usize * PIXEL_SIZE);
+        let mut bytes = vec![0; padded_row_size * image_size.y as usize];
+
+        let mut header = Vec::new();
+        header.extend(size.x.to_le_bytes());
+        header.extend(size.y.to_le_bytes());
+        for value in clip_from_world.to_cols_array() {
+            header.extend(value.to_le_bytes());
+        }
+        bytes[..header.len()].copy_from_slice(&header);
+
+        for (i, texel) in texels.iter().enumerate() {
+            let offset =
+                (i / size.x as usize + 1) * padded_row_size + (i % size.x as usize) * PIXEL_SIZE;
+            for (channel, value) in texel.to_array().into_iter().enumerate() {
+                bytes[offset + channel * 2..offset + channel * 2 + 2]
+                    .copy_from_slice(&f16::from_f32(value).to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn decodes_header_and_texels() {
+        let size = UVec2::new(3, 2);
+        let clip_from_world = Mat4::orthographic_rh(-4.0, 4.0, -2.0, 2.0, 0.0, 1.0);
+        let texels: Vec<Vec4> = (0..6)
+            .map(|i| Vec4::new(i as f32 + 0.5, -1.0, 1.0, 0.25))
+            .collect();
+
+        let (decoded_size, decoded_clip_from_world, decoded_texels) =
+            decode_readback(&encode_readback(size, clip_from_world, &texels)).unwrap();
+
+        assert_eq!(decoded_size, size);
+        assert_eq!(decoded_clip_from_world, clip_from_world);
+        assert_eq!(decoded_texels, texels);
+    }
+
+    #[test]
+    fn ignores_images_without_a_field() {
+        let bytes = vec![0; 256 * 2];
+        assert!(decode_readback(&bytes).is_none());
+        assert!(decode_readback(&[]).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let size = UVec2::new(4, 4);
+        let bytes = encode_readback(size, Mat4::IDENTITY, &[Vec4::ZERO; 16]);
+        // Without the last row
+        let padded_row_size =
+            RenderDevice::align_copy_bytes_per_row(HEADER_TEXELS as usize * PIXEL_SIZE);
+        assert!(decode_readback(&bytes[..bytes.len() - padded_row_size]).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::weak_handle,
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    render::{
+        render_resource::{
+            binding_types::uniform_buffer, BindGroupEntries, BindGroupLayout,
+            BindGroupLayoutEntries, BufferUsages, BufferVec, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, FragmentState, LoadOp, MultisampleState, Operations,
+            PipelineCache, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, ShaderStages, ShaderType, StoreOp,
+            TextureFormat, UniformBuffer, VertexAttribute, VertexBufferLayout, VertexFormat,
+            VertexState, VertexStepMode,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        view::{ExtractedView, RenderLayers},
+    },
+};
+
+use crate::{
+    flood::VoronoiWeight,
+    plugin::{VoronoiCamera, VoronoiTexture},
+};
+
+pub const POINT_SEED_SHADER: Handle<Shader> = weak_handle!("3f0b8e5c-29a4-4d8e-9b61-6f4f2c1de7a3");
+
+/// A point seed of the Voronoi diagram, splatted directly into the seed texture at its
+/// `GlobalTransform` translation without going through the mask pass.
+#[derive(Component, Clone, Default)]
+#[require(Transform, Visibility)]
+pub struct VoronoiSeed {
+    /// Object id propagated alongside the seed. Defaults to the entity index.
+    pub id: Option<u32>,
+    pub weight: VoronoiWeight,
+}
+
+impl VoronoiSeed {
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: VoronoiWeight) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+#[derive(ShaderType, Clone, Copy, Default, PartialEq, Debug)]
+pub struct PointSeedVertex {
+    pub position: Vec3,
+    pub id: u32,
+    /// Multiplicative and additive weight, see [`VoronoiWeight`].
+    pub weight: Vec2,
+}
+
+#[derive(Resource)]
+pub struct PointSeedPipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for PointSeedPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "point_seed_bind_group_layout",
+            &BindGroupLayoutEntries::single(ShaderStages::VERTEX, uniform_buffer::<Mat4>(false)),
+        );
+
+        let pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("point_seed_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: VertexState {
+                        shader: POINT_SEED_SHADER,
+                        shader_defs: vec![],
+                        entry_point: "vertex".into(),
+                        buffers: vec![VertexBufferLayout {
+                            array_stride: PointSeedVertex::min_size().get(),
+                            step_mode: VertexStepMode::Vertex,
+                            attributes: vec![
+                                VertexAttribute {
+                                    format: VertexFormat::Float32x3,
+                                    offset: 0,
+                                    shader_location: 0,
+                                },
+                                VertexAttribute {
+                                    format: VertexFormat::Uint32,
+                                    offset: 12,
+                                    shader_location: 1,
+                                },
+                                VertexAttribute {
+                                    format: VertexFormat::Float32x2,
+                                    offset: 16,
+                                    shader_location: 2,
+                                },
+                            ],
+                        }],
+                    },
+                    fragment: Some(FragmentState {
+                        shader: POINT_SEED_SHADER,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba16Float,
+                                blend: None,
+                                write_mask: ColorWrites::COLOR,
+                            }),
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba32Uint,
+                                blend: None,
+                                write_mask: ColorWrites::ALL,
+                            }),
+                        ],
+                    }),
+                    push_constant_ranges: vec![],
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::PointList,
+                        ..default()
+                    },
+                    depth_stencil: None,
+                    multisample: MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    zero_initialize_workgroup_memory: false,
+                });
+
+        Self { layout, pipeline }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderPointSeed {
+    pub vertex: PointSeedVertex,
+    pub layers: RenderLayers,
+}
+
+#[derive(Resource)]
+pub struct RenderPointSeeds {
+    pub seeds: Vec<RenderPointSeed>,
+    pub buffer: BufferVec<PointSeedVertex>,
+    /// Vertices of `buffer` drawn by each view, seeds are repeated for every view whose
+    /// [`VoronoiCamera::layers`] they intersect.
+    pub view_ranges: EntityHashMap<Range<u32>>,
+}
+
+impl Default for RenderPointSeeds {
+    fn default() -> Self {
+        Self {
+            seeds: Vec::new(),
+            buffer: BufferVec::new(BufferUsages::VERTEX),
+            view_ranges: EntityHashMap::default(),
+        }
+    }
+}
+
+impl RenderPointSeeds {
+    pub fn view_seeds<'a>(
+        &'a self,
+        voronoi_camera: &'a VoronoiCamera,
+    ) -> impl Iterator<Item = PointSeedVertex> + 'a {
+        self.seeds
+            .iter()
+            .filter(|seed| voronoi_camera.layers.intersects(&seed.layers))
+            .map(|seed| seed.vertex)
+    }
+}
+
+pub fn prepare_point_seeds(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    views: Query<(Entity, &VoronoiCamera)>,
+    mut point_seeds: ResMut<RenderPointSeeds>,
+) {
+    let RenderPointSeeds {
+        seeds,
+        buffer,
+        view_ranges,
+    } = point_seeds.as_mut();
+
+    buffer.clear();
+    view_ranges.clear();
+    for (view_entity, voronoi_camera) in &views {
+        let start = buffer.len() as u32;
+        for seed in seeds.iter() {
+            if voronoi_camera.layers.intersects(&seed.layers) {
+                buffer.push(seed.vertex);
+            }
+        }
+        view_ranges.insert(view_entity, start..buffer.len() as u32);
+    }
+    buffer.write_buffer(&render_device, &render_queue);
+}
+
+/// Splats point seeds into the output of the seed pass, keeping its alpha mask.
+pub fn run_point_seed_pass<'w>(
+    world: &'w World,
+    render_context: &mut RenderContext<'w>,
+    view_entity: Entity,
+    view: &ExtractedView,
+    voronoi_textures: &VoronoiTexture,
+) {
+    let point_seeds = world.resource::<RenderPointSeeds>();
+    let Some(vertex_buffer) = point_seeds.buffer.buffer() else {
+        return;
+    };
+
+    let Some(vertices) = point_seeds.view_ranges.get(&view_entity).cloned() else {
+        return;
+    };
+
+    if vertices.is_empty() {
+        return;
+    }
+
+    let point_seed_pipeline = world.resource::<PointSeedPipeline>();
+
+    let clip_from_world = view
+        .clip_from_world
+        .unwrap_or_else(|| view.clip_from_view * view.world_from_view.compute_matrix().inverse());
+    let mut clip_from_world = UniformBuffer::from(clip_from_world);
+
+    clip_from_world.write_buffer(
+        render_context.render_device(),
+        world.resource::<RenderQueue>(),
+    );
+
+    let (Some(pipeline), Some(clip_from_world)) = (
+        world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(point_seed_pipeline.pipeline),
+        clip_from_world.binding(),
+    ) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "point_seed_bind_group",
+        &point_seed_pipeline.layout,
+        &BindGroupEntries::single(clip_from_world),
+    );
+
+    let load = Operations {
+        load: LoadOp::Load,
+        store: StoreOp::Store,
+    };
+
+    let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("point_seed_pass"),
+        color_attachments: &[
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.output().default_view,
+                resolve_target: None,
+                ops: load,
+            }),
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.object_output().default_view,
+                resolve_target: None,
+                ops: load,
+            }),
+        ],
+        ..default()
+    });
+
+    voronoi_textures.set_scissor(&mut pass);
+    pass.set_render_pipeline(pipeline);
+    pass.set_bind_group(0, &bind_group, &[]);
+    pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+    pass.draw(vertices, 0..1);
+}
@@ -0,0 +1,546 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::weak_handle,
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferUsages, BufferVec,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, LoadOp,
+            MultisampleState, Operations, PipelineCache, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor,
+            ShaderStages, ShaderType, StoreOp, TextureFormat, TextureSampleType, UniformBuffer,
+            VertexAttribute, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::GpuImage,
+        view::{ExtractedView, RenderLayers},
+    },
+};
+
+use crate::{
+    mask::MaskMaterialUniform,
+    plugin::{VoronoiCamera, VoronoiTexture},
+};
+
+pub const SPRITE_MASK_SHADER: Handle<Shader> = weak_handle!("5e2a7c91-8d43-4b6f-a0e5-3c9b1f7d2e68");
+
+/// World space quad of a sprite drawn into the mask pass, one per instance.
+#[derive(ShaderType, Clone, Copy, Default, PartialEq, Debug)]
+pub struct SpriteMaskInstance {
+    /// World position of the top left corner of the sprite.
+    pub origin: Vec3,
+    pub id: u32,
+    /// Edge from the top left to the top right corner.
+    pub x_axis: Vec3,
+    pub channel: u32,
+    /// Edge from the top left to the bottom left corner.
+    pub y_axis: Vec3,
+    pub flags: u32,
+    /// Texture coordinates of the top left and bottom right corners.
+    pub uv_rect: Vec4,
+    /// Multiplicative and additive weight, see [`VoronoiWeight`](crate::prelude::VoronoiWeight).
+    pub weight: Vec2,
+    pub threshold: f32,
+}
+
+impl SpriteMaskInstance {
+    /// Computes the quad the same way `bevy_sprite` does, honouring the atlas and sprite
+    /// rects, `custom_size`, flips and the anchor. `image_mode` is ignored.
+    pub fn new(
+        sprite: &Sprite,
+        transform: &GlobalTransform,
+        image_size: Vec2,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+        uniform: MaskMaterialUniform,
+    ) -> Self {
+        let atlas_rect = sprite
+            .texture_atlas
+            .as_ref()
+            .and_then(|atlas| atlas.texture_rect(texture_atlases))
+            .map(|rect| rect.as_rect());
+
+        // The sprite rect is relative to the atlas rect
+        let rect = match (atlas_rect, sprite.rect) {
+            (None, None) => None,
+            (None, Some(sprite_rect)) => Some(sprite_rect),
+            (Some(atlas_rect), None) => Some(atlas_rect),
+            (Some(atlas_rect), Some(mut sprite_rect)) => {
+                sprite_rect.min += atlas_rect.min;
+                sprite_rect.max += atlas_rect.min;
+                Some(sprite_rect)
+            }
+        };
+
+        let rect = rect.unwrap_or(Rect::from_corners(Vec2::ZERO, image_size));
+        let size = sprite.custom_size.unwrap_or(rect.size());
+
+        let mut uv_min = rect.min / image_size;
+        let mut uv_max = rect.max / image_size;
+        if sprite.flip_x {
+            std::mem::swap(&mut uv_min.x, &mut uv_max.x);
+        }
+        if sprite.flip_y {
+            std::mem::swap(&mut uv_min.y, &mut uv_max.y);
+        }
+
+        // Local position of a corner, given in texture space of the unflipped quad
+        let anchor = sprite.anchor.as_vec();
+        let corner = |uv: Vec2| {
+            let local = Vec2::new(uv.x - 0.5 - anchor.x, 0.5 - uv.y - anchor.y) * size;
+            transform.transform_point(local.extend(0.0))
+        };
+
+        let origin = corner(Vec2::ZERO);
+
+        Self {
+            origin,
+            id: uniform.id,
+            x_axis: corner(Vec2::X) - origin,
+            channel: uniform.channel,
+            y_axis: corner(Vec2::Y) - origin,
+            flags: uniform.flags,
+            uv_rect: Vec4::new(uv_min.x, uv_min.y, uv_max.x, uv_max.y),
+            weight: Vec2::new(uniform.multiplicative_weight, uniform.additive_weight),
+            threshold: uniform.threshold,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SpriteMaskPipeline {
+    pub view_layout: BindGroupLayout,
+    pub texture_layout: BindGroupLayout,
+    pub pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for SpriteMaskPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let view_layout = render_device.create_bind_group_layout(
+            "sprite_mask_view_bind_group_layout",
+            &BindGroupLayoutEntries::single(ShaderStages::VERTEX, uniform_buffer::<Mat4>(false)),
+        );
+
+        let texture_layout = render_device.create_bind_group_layout(
+            "sprite_mask_texture_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        let attribute = |format, offset, shader_location| VertexAttribute {
+            format,
+            offset,
+            shader_location,
+        };
+
+        let pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("sprite_mask_pipeline".into()),
+                    layout: vec![view_layout.clone(), texture_layout.clone()],
+                    vertex: VertexState {
+                        shader: SPRITE_MASK_SHADER,
+                        shader_defs: vec![],
+                        entry_point: "vertex".into(),
+                        buffers: vec![VertexBufferLayout {
+                            array_stride: SpriteMaskInstance::min_size().get(),
+                            step_mode: VertexStepMode::Instance,
+                            attributes: vec![
+                                attribute(VertexFormat::Float32x3, 0, 0),
+                                attribute(VertexFormat::Uint32, 12, 1),
+                                attribute(VertexFormat::Float32x3, 16, 2),
+                                attribute(VertexFormat::Uint32, 28, 3),
+                                attribute(VertexFormat::Float32x3, 32, 4),
+                                attribute(VertexFormat::Uint32, 44, 5),
+                                attribute(VertexFormat::Float32x4, 48, 6),
+                                attribute(VertexFormat::Float32x2, 64, 7),
+                                attribute(VertexFormat::Float32, 72, 8),
+                            ],
+                        }],
+                    },
+                    fragment: Some(FragmentState {
+                        shader: SPRITE_MASK_SHADER,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba16Float,
+                                blend: None,
+                                write_mask: ColorWrites::ALL,
+                            }),
+                            Some(ColorTargetState {
+                                format: TextureFormat::Rgba32Uint,
+                                blend: None,
+                                write_mask: ColorWrites::ALL,
+                            }),
+                        ],
+                    }),
+                    push_constant_ranges: vec![],
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    zero_initialize_workgroup_memory: false,
+                });
+
+        Self {
+            view_layout,
+            texture_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderVoronoiSprite {
+    pub image: AssetId<Image>,
+    pub instance: SpriteMaskInstance,
+    pub layers: RenderLayers,
+}
+
+#[derive(Resource)]
+pub struct RenderVoronoiSprites {
+    pub sprites: Vec<RenderVoronoiSprite>,
+    pub buffer: BufferVec<SpriteMaskInstance>,
+    /// Instance ranges of `buffer` sharing an image drawn by each view, sprites are
+    /// repeated for every view whose [`VoronoiCamera::layers`] they intersect.
+    pub view_batches: EntityHashMap<Vec<(AssetId<Image>, Range<u32>)>>,
+}
+
+impl Default for RenderVoronoiSprites {
+    fn default() -> Self {
+        Self {
+            sprites: Vec::new(),
+            buffer: BufferVec::new(BufferUsages::VERTEX),
+            view_batches: EntityHashMap::default(),
+        }
+    }
+}
+
+impl RenderVoronoiSprites {
+    pub fn view_sprites<'a>(
+        &'a self,
+        voronoi_camera: &'a VoronoiCamera,
+    ) -> impl Iterator<Item = &'a RenderVoronoiSprite> + 'a {
+        self.sprites
+            .iter()
+            .filter(|sprite| voronoi_camera.layers.intersects(&sprite.layers))
+    }
+}
+
+pub fn prepare_voronoi_sprites(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    views: Query<(Entity, &VoronoiCamera)>,
+    mut voronoi_sprites: ResMut<RenderVoronoiSprites>,
+) {
+    let RenderVoronoiSprites {
+        sprites,
+        buffer,
+        view_batches,
+    } = voronoi_sprites.as_mut();
+
+    sprites.sort_by_key(|sprite| sprite.image);
+
+    buffer.clear();
+    view_batches.clear();
+    for (view_entity, voronoi_camera) in &views {
+        let mut batches: Vec<(AssetId<Image>, Range<u32>)> = Vec::new();
+        for sprite in sprites.iter() {
+            if !voronoi_camera.layers.intersects(&sprite.layers) {
+                continue;
+            }
+
+            let index = buffer.push(sprite.instance) as u32;
+            match batches.last_mut() {
+                Some((image, range)) if *image == sprite.image => range.end = index + 1,
+                _ => batches.push((sprite.image, index..index + 1)),
+            }
+        }
+        view_batches.insert(view_entity, batches);
+    }
+    buffer.write_buffer(&render_device, &render_queue);
+}
+
+/// Draws sprites on top of the output of the mask pass.
+pub fn run_sprite_mask_pass<'w>(
+    world: &'w World,
+    render_context: &mut RenderContext<'w>,
+    view_entity: Entity,
+    view: &ExtractedView,
+    voronoi_textures: &VoronoiTexture,
+) {
+    let voronoi_sprites = world.resource::<RenderVoronoiSprites>();
+    let Some(instance_buffer) = voronoi_sprites.buffer.buffer() else {
+        return;
+    };
+
+    let Some(view_batches) = voronoi_sprites.view_batches.get(&view_entity) else {
+        return;
+    };
+
+    if view_batches.is_empty() {
+        return;
+    }
+
+    let sprite_mask_pipeline = world.resource::<SpriteMaskPipeline>();
+    let images = world.resource::<RenderAssets<GpuImage>>();
+
+    let clip_from_world = view
+        .clip_from_world
+        .unwrap_or_else(|| view.clip_from_view * view.world_from_view.compute_matrix().inverse());
+    let mut clip_from_world = UniformBuffer::from(clip_from_world);
+
+    clip_from_world.write_buffer(
+        render_context.render_device(),
+        world.resource::<RenderQueue>(),
+    );
+
+    let (Some(pipeline), Some(clip_from_world)) = (
+        world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(sprite_mask_pipeline.pipeline),
+        clip_from_world.binding(),
+    ) else {
+        return;
+    };
+
+    let render_device = render_context.render_device().clone();
+
+    let view_bind_group = render_device.create_bind_group(
+        "sprite_mask_view_bind_group",
+        &sprite_mask_pipeline.view_layout,
+        &BindGroupEntries::single(clip_from_world),
+    );
+
+    let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+    let batches: Vec<_> = view_batches
+        .iter()
+        .filter_map(|(image, range)| {
+            let image = images.get(*image)?;
+            let bind_group = render_device.create_bind_group(
+                "sprite_mask_texture_bind_group",
+                &sprite_mask_pipeline.texture_layout,
+                &BindGroupEntries::sequential((&image.texture_view, &sampler)),
+            );
+            Some((bind_group, range.clone()))
+        })
+        .collect();
+
+    let load = Operations {
+        load: LoadOp::Load,
+        store: StoreOp::Store,
+    };
+
+    let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("sprite_mask_pass"),
+        color_attachments: &[
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.output().default_view,
+                resolve_target: None,
+                ops: load,
+            }),
+            Some(RenderPassColorAttachment {
+                view: &voronoi_textures.object_output().default_view,
+                resolve_target: None,
+                ops: load,
+            }),
+        ],
+        ..default()
+    });
+
+    voronoi_textures.set_scissor(&mut pass);
+    pass.set_render_pipeline(pipeline);
+    pass.set_bind_group(0, &view_bind_group, &[]);
+    pass.set_vertex_buffer(0, instance_buffer.slice(..));
+    for (bind_group, range) in &batches {
+        pass.set_bind_group(1, bind_group, &[]);
+        pass.draw(0..6, range.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Affine3A, sprite::Anchor};
+
+    use super::*;
+
+    const IMAGE_SIZE: Vec2 = Vec2::new(64.0, 32.0);
+
+    /// World position and UV of a vertex of the quad `bevy_sprite` draws for a sprite with
+    /// the resolved atlas and sprite `rect`, `vertex` being the unit quad position of
+    /// `sprite.wgsl`.
+    fn bevy_sprite_vertex(
+        sprite: &Sprite,
+        transform: &GlobalTransform,
+        rect: Option<Rect>,
+        vertex: Vec2,
+    ) -> (Vec3, Vec2) {
+        let mut quad_size = IMAGE_SIZE;
+        let mut uv_offset_scale = if let Some(rect) = rect {
+            quad_size = rect.size();
+            Vec4::new(
+                rect.min.x / IMAGE_SIZE.x,
+                rect.max.y / IMAGE_SIZE.y,
+                rect.size().x / IMAGE_SIZE.x,
+                -rect.size().y / IMAGE_SIZE.y,
+            )
+        } else {
+            Vec4::new(0.0, 1.0, 1.0, -1.0)
+        };
+        if sprite.flip_x {
+            uv_offset_scale.x += uv_offset_scale.z;
+            uv_offset_scale.z *= -1.0;
+        }
+        if sprite.flip_y {
+            uv_offset_scale.y += uv_offset_scale.w;
+            uv_offset_scale.w *= -1.0;
+        }
+        quad_size = sprite.custom_size.unwrap_or(quad_size);
+
+        let quad = transform.affine()
+            * Affine3A::from_scale_rotation_translation(
+                quad_size.extend(1.0),
+                Quat::IDENTITY,
+                (quad_size * (-sprite.anchor.as_vec() - Vec2::splat(0.5))).extend(0.0),
+            );
+
+        (
+            quad.transform_point3(vertex.extend(0.0)),
+            vertex * uv_offset_scale.zw() + uv_offset_scale.xy(),
+        )
+    }
+
+    fn assert_matches_bevy(
+        sprite: &Sprite,
+        texture_atlases: &Assets<TextureAtlasLayout>,
+        rect: Option<Rect>,
+    ) {
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(10.0, -20.0, 3.0)
+                .with_rotation(Quat::from_rotation_z(0.3))
+                .with_scale(Vec3::new(2.0, 0.5, 1.0)),
+        );
+        let instance = SpriteMaskInstance::new(
+            sprite,
+            &transform,
+            IMAGE_SIZE,
+            texture_atlases,
+            MaskMaterialUniform::default(),
+        );
+
+        let (top_left, top_left_uv) = bevy_sprite_vertex(sprite, &transform, rect, Vec2::Y);
+        let (top_right, _) = bevy_sprite_vertex(sprite, &transform, rect, Vec2::ONE);
+        let (bottom_left, _) = bevy_sprite_vertex(sprite, &transform, rect, Vec2::ZERO);
+        let (_, bottom_right_uv) = bevy_sprite_vertex(sprite, &transform, rect, Vec2::X);
+
+        assert!(instance.origin.abs_diff_eq(top_left, 1e-4));
+        assert!(instance.x_axis.abs_diff_eq(top_right - top_left, 1e-4));
+        assert!(instance.y_axis.abs_diff_eq(bottom_left - top_left, 1e-4));
+        assert!(instance.uv_rect.xy().abs_diff_eq(top_left_uv, 1e-6));
+        assert!(instance.uv_rect.zw().abs_diff_eq(bottom_right_uv, 1e-6));
+    }
+
+    #[test]
+    fn plain_sprites_match_bevy() {
+        let texture_atlases = Assets::default();
+        assert_matches_bevy(&Sprite::default(), &texture_atlases, None);
+        assert_matches_bevy(
+            &Sprite {
+                custom_size: Some(Vec2::new(10.0, 40.0)),
+                ..default()
+            },
+            &texture_atlases,
+            None,
+        );
+    }
+
+    #[test]
+    fn flipped_sprites_match_bevy() {
+        let texture_atlases = Assets::default();
+        for (flip_x, flip_y) in [(true, false), (false, true), (true, true)] {
+            let sprite = Sprite {
+                flip_x,
+                flip_y,
+                ..default()
+            };
+            assert_matches_bevy(&sprite, &texture_atlases, None);
+        }
+    }
+
+    #[test]
+    fn anchored_sprites_match_bevy() {
+        let texture_atlases = Assets::default();
+        for anchor in [
+            Anchor::BottomLeft,
+            Anchor::TopRight,
+            Anchor::CenterLeft,
+            Anchor::Custom(Vec2::new(0.3, -0.1)),
+        ] {
+            let sprite = Sprite {
+                anchor,
+                flip_x: true,
+                ..default()
+            };
+            assert_matches_bevy(&sprite, &texture_atlases, None);
+        }
+    }
+
+    #[test]
+    fn atlas_sprites_match_bevy() {
+        let mut texture_atlases = Assets::default();
+        let layout = texture_atlases.add(TextureAtlasLayout::from_grid(
+            UVec2::splat(16),
+            4,
+            2,
+            None,
+            None,
+        ));
+        let atlas = |index| TextureAtlas {
+            layout: layout.clone(),
+            index,
+        };
+
+        // Tile 5 is the second of the bottom row
+        let tile = Rect::new(16.0, 16.0, 32.0, 32.0);
+        let sprite = Sprite {
+            texture_atlas: Some(atlas(5)),
+            anchor: Anchor::TopCenter,
+            flip_y: true,
+            ..default()
+        };
+        assert_matches_bevy(&sprite, &texture_atlases, Some(tile));
+
+        // The sprite rect is relative to the tile
+        let sprite = Sprite {
+            texture_atlas: Some(atlas(5)),
+            rect: Some(Rect::new(2.0, 4.0, 10.0, 12.0)),
+            ..default()
+        };
+        assert_matches_bevy(
+            &sprite,
+            &texture_atlases,
+            Some(Rect::new(18.0, 20.0, 26.0, 28.0)),
+        );
+
+        let sprite = Sprite {
+            rect: Some(Rect::new(8.0, 0.0, 40.0, 16.0)),
+            ..default()
+        };
+        assert_matches_bevy(&sprite, &texture_atlases, sprite.rect);
+    }
+}
@@ -0,0 +1,69 @@
+#import bevy_voronoi::mask::mask_coverage
+
+@group(0) @binding(0) var<uniform> clip_from_world: mat4x4<f32>;
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1) var sprite_sampler: sampler;
+
+struct VertexInput {
+    @builtin(vertex_index) index: u32,
+    @location(0) origin: vec3<f32>,
+    @location(1) id: u32,
+    @location(2) x_axis: vec3<f32>,
+    @location(3) channel: u32,
+    @location(4) y_axis: vec3<f32>,
+    @location(5) flags: u32,
+    @location(6) uv_rect: vec4<f32>,
+    @location(7) weight: vec2<f32>,
+    @location(8) threshold: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) id: u32,
+    @location(2) @interpolate(flat) channel: u32,
+    @location(3) @interpolate(flat) flags: u32,
+    @location(4) @interpolate(flat) weight: vec2<f32>,
+    @location(5) @interpolate(flat) threshold: f32,
+}
+
+struct FragmentOutput {
+    @location(0) mask: vec4<f32>,
+    @location(1) object: vec4<u32>,
+}
+
+@vertex
+fn vertex(in: VertexInput) -> VertexOutput {
+    // Two triangles, with the corners relative to the top left corner of the sprite
+    let corners = array<vec2<f32>, 6>(
+        vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+        vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+    );
+    let corner = corners[in.index];
+    let position = in.origin + corner.x * in.x_axis + corner.y * in.y_axis;
+
+    var out: VertexOutput;
+    out.position = clip_from_world * vec4(position, 1.0);
+    out.uv = mix(in.uv_rect.xy, in.uv_rect.zw, corner);
+    out.id = in.id;
+    out.channel = in.channel;
+    out.flags = in.flags;
+    out.weight = in.weight;
+    out.threshold = in.threshold;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    let color = textureSample(sprite_texture, sprite_sampler, in.uv);
+    let mask = mask_coverage(color, in.channel, in.flags, in.threshold);
+
+    if mask <= 0. {
+        discard;
+    }
+
+    var out: FragmentOutput;
+    out.mask = vec4(mask);
+    out.object = vec4(in.id, bitcast<vec2<u32>>(in.weight), 0u);
+    return out;
+}